@@ -0,0 +1,38 @@
+fn func_exclude(a: i32, b: i32) -> i32 {
+    // rust-cov:ignore-next-line
+    debug_assert!(a >= 0);
+    if a > b {
+        a
+    } else {
+        b
+    }
+}
+
+#[coverage(off)]
+fn unreachable_helper(x: i32) -> i32 {
+    if x > 0 {
+        x
+    } else {
+        -x
+    }
+}
+
+#[coverage(off)]
+mod debug {
+    pub fn dump(x: i32) {
+        println!("{}", x);
+    }
+
+    #[coverage(on)]
+    pub fn check(x: i32) -> bool {
+        x > 0 && x < 10
+    }
+}
+
+fn func_region(x: i32) -> i32 {
+    // rust-cov:ignore-start
+    let y = x * 2;
+    println!("{}", y);
+    // rust-cov:ignore-end
+    x + 1
+}
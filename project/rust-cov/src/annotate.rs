@@ -0,0 +1,64 @@
+use std::io::IsTerminal;
+use chalk_rs::Chalk;
+
+use crate::summary::{Branch, FileCoverage};
+
+// Prints every file with gutters for line numbers and hit counts. Lines of
+// covered statements are green, uncovered ones red, and decisions or
+// conditions with arms never taken yellow, underlined with those arms. Colors
+// are left out when stdout is not a terminal.
+pub fn print(files: &[FileCoverage]) {
+    let color = std::io::stdout().is_terminal();
+    for file in files {
+        print_file(file, color);
+    }
+}
+
+fn paint(color: bool, text: &str, style: fn(&mut Chalk) -> &mut Chalk) -> String {
+    if !color {
+        return text.to_string();
+    }
+    let mut chalk = Chalk::new();
+    style(&mut chalk).string(&text)
+}
+
+// Some arms taken but not all of them.
+fn partial(branch: &Branch) -> bool {
+    branch.covered() > 0 && branch.covered() < branch.arms.len()
+}
+
+fn print_file(file: &FileCoverage, color: bool) {
+    println!("{} lines {}/{}, branches {}/{}", paint(color, &file.path, Chalk::bold),
+             file.lines_covered(), file.lines.len(), file.arms_covered(), file.arms());
+
+    let number_width = file.source.lines().count().to_string().len();
+    let hits_width = file.lines.values().max().map_or(1, |max| max.to_string().len());
+    let gutter = |number: &str, hits: &str| paint(color, &format!("{:>w$} │ {:>h$} │ ", number, hits, w = number_width, h = hits_width), Chalk::gray);
+
+    for (idx, text) in file.source.lines().enumerate() {
+        let number = idx + 1;
+        let branches: Vec<&Branch> = file.branches.iter().filter(|branch| branch.line == number && partial(branch)).collect();
+        let code = match file.lines.get(&number) {
+            Some(0) => paint(color, text, Chalk::red),
+            Some(_) if !branches.is_empty() => paint(color, text, Chalk::yellow),
+            Some(_) => paint(color, text, Chalk::green),
+            None => text.to_string(),
+        };
+        let hits = file.lines.get(&number).map_or(String::new(), |count| count.to_string());
+        println!("{}{}", gutter(&number.to_string(), &hits), code);
+
+        for branch in branches {
+            // keep tabs so the markers line up with the code above
+            let indent: String = text.chars().take(branch.span.1).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let end = if branch.span.2 == number { branch.span.3 } else { text.chars().count() };
+            let underline = "^".repeat(end.saturating_sub(branch.span.1).max(1));
+            let missed: Vec<String> = branch.arm_names.iter().zip(&branch.arms)
+                .filter(|(_, taken)| **taken == Some(0))
+                .map(|(name, _)| name.clone())
+                .collect();
+            let note = format!("{}{} `{}`: {} never taken", indent, underline, branch.text, missed.join(", "));
+            println!("{}{}", gutter("", ""), paint(color, &note, Chalk::yellow));
+        }
+    }
+    println!();
+}
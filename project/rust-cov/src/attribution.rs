@@ -0,0 +1,132 @@
+use std::collections::BTreeSet;
+use syn::visit::{self, Visit};
+
+use crate::cfg::{contains, span_of, Cfg};
+use crate::edge::EdgeCoverage;
+use crate::hits::LineHits;
+use crate::mutation::is_cfg_test;
+use crate::{snippet, Coverage, Span};
+
+// A coverage item and the tests that reached it.
+pub struct Item {
+    pub label: String,
+    pub tests: BTreeSet<String>,
+}
+
+// The functions, statements and branches of a file with the tests reaching
+// each of them.
+pub struct Items {
+    pub functions: Vec<Item>,
+    pub statements: Vec<Item>,
+    pub branches: Vec<Item>,
+}
+
+fn attribute(traces: &[(String, Vec<usize>)], label: String, covers: impl Fn(&[usize]) -> bool) -> Item {
+    let tests = traces.iter().filter(|(_, trace)| covers(trace)).map(|(name, _)| name.clone()).collect();
+    Item { label, tests }
+}
+
+// The tests themselves, and items declared inside function bodies, which never
+// execute.
+struct NotAttributed {
+    tests: Vec<Span>,
+    items: BTreeSet<Span>,
+}
+
+impl NotAttributed {
+    fn skips(&self, span: &Span) -> bool {
+        self.items.contains(span) || self.tests.iter().any(|test| contains(test, span))
+    }
+}
+
+impl<'ast> Visit<'ast> for NotAttributed {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        if i.attrs.iter().any(|attr| attr.path().is_ident("test")) {
+            self.tests.push(span_of(i));
+        }
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if is_cfg_test(&i.attrs) {
+            self.tests.push(span_of(i));
+        }
+        visit::visit_item_mod(self, i);
+    }
+
+    fn visit_stmt(&mut self, s: &'ast syn::Stmt) {
+        if let Some(span) = crate::stmt_span(s).filter(|_| matches!(s, syn::Stmt::Item(_))) {
+            self.items.insert(span);
+        }
+        visit::visit_stmt(self, s);
+    }
+}
+
+pub fn items(source: &str, file: &syn::File, coverage: &Coverage, cfgs: &[Cfg], traces: &[(String, Vec<usize>)]) -> Items {
+    let mut skipped = NotAttributed { tests: Vec::new(), items: BTreeSet::new() };
+    skipped.visit_file(file);
+    let cfgs: Vec<&Cfg> = cfgs.iter().filter(|cfg| !skipped.skips(&cfg.span)).collect();
+
+    let functions: Vec<Item> = cfgs.iter()
+        .map(|cfg| {
+            let lines: BTreeSet<usize> = cfg.blocks.iter().flat_map(|b| b.spans.iter().map(|span| span.0)).collect();
+            attribute(traces, format!("{} ({})", cfg.name, cfg.span.0), |trace| trace.iter().any(|l| lines.contains(l)))
+        })
+        .collect();
+
+    let statements: Vec<Item> = coverage.stmt_cov.values()
+        .filter(|span| !skipped.skips(span))
+        .map(|span| {
+            let label = format!("{}:{}-{}:{} `{}`", span.0, span.1, span.2, span.3, snippet(source, span));
+            attribute(traces, label, |trace| trace.contains(&span.0))
+        })
+        .collect();
+
+    // branch edges taken per test, from each test's own line counts
+    let taken: Vec<(String, Vec<Vec<u64>>)> = traces.iter()
+        .map(|(name, trace)| {
+            let hits = LineHits::from_trace(trace);
+            (name.clone(), cfgs.iter().map(|cfg| EdgeCoverage::new(cfg, &hits).counts).collect())
+        })
+        .collect();
+    let mut branches = Vec::new();
+    for (c, cfg) in cfgs.iter().enumerate() {
+        for (e, edge) in cfg.edges.iter().enumerate() {
+            let Some((span, cond)) = &edge.cond else {
+                continue;
+            };
+            let tests = taken.iter().filter(|(_, counts)| counts[c][e] > 0).map(|(name, _)| name.clone()).collect();
+            branches.push(Item { label: format!("{}:{} `{}` → {}", cfg.name, span.0, cond, edge.kind), tests });
+        }
+    }
+
+    Items { functions, statements, branches }
+}
+
+pub fn report(source: &str, file: &syn::File, coverage: &Coverage, cfgs: &[Cfg], traces: &[(String, Vec<usize>)]) {
+    let Items { functions, statements, branches } = items(source, file, coverage, cfgs, traces);
+    println!("Per-test coverage:");
+    for (kind, items) in [("functions", &functions), ("statements", &statements), ("branches", &branches)] {
+        println!("- {}:", kind);
+        for item in items {
+            let marker = if item.tests.is_empty() { '-' } else { '*' };
+            let tests = if item.tests.is_empty() { "(none)".to_string() } else {
+                item.tests.iter().cloned().collect::<Vec<_>>().join(", ")
+            };
+            println!("  {} {}: {}", marker, item.label, tests);
+        }
+    }
+
+    println!("- tests:");
+    for (name, _) in traces {
+        let covered = |items: &[Item]| items.iter().filter(|i| i.tests.contains(name)).map(|i| i.label.clone()).collect::<Vec<_>>();
+        let (funcs, stmts, brs) = (covered(&functions), covered(&statements), covered(&branches));
+        println!("  - {}: {}/{} functions, {}/{} statements, {}/{} branches", name,
+                 funcs.len(), functions.len(), stmts.len(), statements.len(), brs.len(), branches.len());
+        for (kind, labels) in [("function", funcs), ("statement", stmts), ("branch", brs)] {
+            for label in labels {
+                println!("    - {} {}", kind, label);
+            }
+        }
+    }
+}
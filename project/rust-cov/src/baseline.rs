@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::cobertura::module_path;
+use crate::identity::normalize;
+use crate::summary::FileCoverage;
+use crate::{snippet, Span};
+
+// (covered, total) of every kind of item in one file.
+#[derive(Serialize, Deserialize, Clone, Copy, Default)]
+pub struct Totals {
+    pub functions: (usize, usize),
+    pub statements: (usize, usize),
+    pub branches: (usize, usize),
+    pub loops: (usize, usize),
+}
+
+impl Totals {
+    pub fn of(file: &FileCoverage) -> Self {
+        Self {
+            functions: (file.functions_covered(), file.functions.len()),
+            statements: (file.statements_covered(), file.statements.len()),
+            branches: (file.arms_covered(), file.arms()),
+            loops: (file.loops_covered(), file.loops.len()),
+        }
+    }
+
+    pub fn add(&mut self, other: &Totals) {
+        for (sum, (covered, total)) in self.kinds_mut().into_iter().zip(other.kinds()) {
+            sum.0 += covered;
+            sum.1 += total;
+        }
+    }
+
+    pub fn kinds(&self) -> [(usize, usize); 4] {
+        [self.functions, self.statements, self.branches, self.loops]
+    }
+
+    fn kinds_mut(&mut self) -> [&mut (usize, usize); 4] {
+        [&mut self.functions, &mut self.statements, &mut self.branches, &mut self.loops]
+    }
+}
+
+const KINDS: [&str; 4] = ["functions", "statements", "branches", "loops"];
+
+// An item is known by its kind, the function it is in and its code with
+// whitespace and comments normalized away, so it keeps its identity when lines
+// are added around it. `line` only locates it in the report.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Item {
+    pub kind: String,
+    pub function: String,
+    pub code: String,
+    pub line: usize,
+    pub covered: bool,
+}
+
+impl Item {
+    fn key(&self) -> (&str, &str, &str) {
+        (&self.kind, &self.function, &self.code)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct FileSnapshot {
+    pub totals: Totals,
+    pub items: Vec<Item>,
+}
+
+// The coverage of a run by file path, to compare later runs against.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Snapshot {
+    pub files: BTreeMap<String, FileSnapshot>,
+}
+
+impl Snapshot {
+    pub fn new(files: &[FileCoverage]) -> Self {
+        let files = files.iter()
+            .map(|file| (file.path.clone(), FileSnapshot { totals: Totals::of(file), items: items(file) }))
+            .collect();
+        Self { files }
+    }
+
+    pub fn load(path: &str) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self, path: &str) {
+        let json = serde_json::to_string_pretty(self).expect("Something went wrong serializing the snapshot");
+        fs::write(path, json).expect("Something went wrong writing the snapshot file");
+    }
+
+    pub fn total(&self) -> Totals {
+        let mut total = Totals::default();
+        self.files.values().for_each(|file| total.add(&file.totals));
+        total
+    }
+}
+
+fn items(file: &FileCoverage) -> Vec<Item> {
+    let module = module_path(&file.path);
+    // the innermost function containing a line
+    let function_at = |line: usize| file.functions.iter()
+        .filter(|f| (f.span.0..=f.span.2).contains(&line))
+        .min_by_key(|f| f.span.2 - f.span.0)
+        .map_or(module.clone(), |f| format!("{}::{}", module, f.name));
+    let item = |kind: &str, line: usize, code: String, covered: bool| Item {
+        kind: kind.to_string(), function: function_at(line), code, line, covered,
+    };
+    let code = |span: &Span| normalize(&snippet(&file.source, span));
+
+    let mut items = Vec::new();
+    for function in &file.functions {
+        items.push(item("function", function.span.0, normalize(&file.signature(function)), function.hits > 0));
+    }
+    for (span, count) in &file.statements {
+        items.push(item("statement", span.0, code(span), *count > 0));
+    }
+    for branch in &file.branches {
+        for (name, taken) in branch.arm_names.iter().zip(&branch.arms) {
+            let code = format!("{} => {}", normalize(&branch.text), normalize(name));
+            items.push(item("branch", branch.line, code, taken.unwrap_or(0) > 0));
+        }
+    }
+    for (span, count) in &file.loops {
+        items.push(item("loop", span.0, loop_header(file, span), *count > 0));
+    }
+    items
+}
+
+// The loop up to its body, the body's statements are items of their own.
+fn loop_header(file: &FileCoverage, span: &Span) -> String {
+    let text = snippet(&file.source, span);
+    let header = text.split_once('{').map_or(text.as_str(), |(header, _)| header);
+    normalize(header)
+}
+
+// Items matched by key, the n-th occurrence of a key with the n-th one in the
+// baseline.
+fn matched<'a>(old: &'a [Item], new: &'a [Item]) -> Vec<(Option<&'a Item>, &'a Item)> {
+    let mut remaining: BTreeMap<(&str, &str, &str), Vec<&Item>> = BTreeMap::new();
+    for item in old.iter().rev() {
+        remaining.entry(item.key()).or_default().push(item);
+    }
+    new.iter()
+        .map(|item| (remaining.get_mut(&item.key()).and_then(Vec::pop), item))
+        .collect()
+}
+
+// Prints the items covered in `baseline` and uncovered now, the uncovered
+// items the baseline does not have, and every percentage that dropped.
+// Returns whether coverage regressed.
+pub fn compare(baseline: &Snapshot, files: &[FileCoverage]) -> bool {
+    let current = Snapshot::new(files);
+    let mut regressed = Vec::new();
+    let mut uncovered_new = Vec::new();
+    let mut drops = Vec::new();
+    for (path, file) in &current.files {
+        let Some(old) = baseline.files.get(path) else {
+            uncovered_new.extend(file.items.iter().filter(|item| !item.covered).map(|item| (path, item)));
+            continue;
+        };
+        for (before, item) in matched(&old.items, &file.items) {
+            match before {
+                Some(before) if before.covered && !item.covered => regressed.push((path, item)),
+                None if !item.covered => uncovered_new.push((path, item)),
+                _ => {}
+            }
+        }
+        for (kind, (now, then)) in KINDS.iter().zip(file.totals.kinds().into_iter().zip(old.totals.kinds())) {
+            let (now_percent, then_percent) = (percent(now), percent(then));
+            if now_percent < then_percent - 0.005 {
+                drops.push(format!("{}: {} {:.2}% -> {:.2}% ({}/{} -> {}/{})", path, kind, then_percent, now_percent, then.0, then.1, now.0, now.1));
+            }
+        }
+    }
+
+    let describe = |(path, item): &(&String, &Item)| format!("{}:{} {} `{}` in {}", path, item.line, item.kind, item.code, item.function);
+    println!("Covered before, uncovered now: {}", regressed.len());
+    for item in &regressed {
+        println!("- {}", describe(item));
+    }
+    println!("New uncovered items: {}", uncovered_new.len());
+    for item in &uncovered_new {
+        println!("- {}", describe(item));
+    }
+    println!("Coverage drops: {}", drops.len());
+    for drop in &drops {
+        println!("- {}", drop);
+    }
+    !regressed.is_empty() || !drops.is_empty()
+}
+
+fn percent((covered, total): (usize, usize)) -> f64 {
+    if total == 0 { 100.0 } else { covered as f64 / total as f64 * 100.0 }
+}
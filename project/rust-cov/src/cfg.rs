@@ -0,0 +1,479 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt;
+use quote::ToTokens;
+use serde::{Deserialize, Serialize};
+use syn::{spanned::Spanned, visit::{self, Visit}, Block, Expr, Stmt};
+
+use crate::Span;
+
+pub const ENTRY: usize = 0;
+pub const EXIT: usize = 1;
+
+// span and source text of a condition, scrutinee or pattern
+pub type Decision = (Span, String);
+
+#[derive(Serialize, Deserialize)]
+pub struct Cfg {
+    pub name: String,
+    pub span: Span,
+    pub blocks: Vec<BasicBlock>,
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BasicBlock {
+    pub id: usize,
+    // owned spans of the statements / conditions evaluated in this block
+    pub spans: Vec<Span>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub kind: EdgeKind,
+    // the condition, scrutinee or pattern that selects this edge
+    pub cond: Option<Decision>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum EdgeKind {
+    Goto,
+    True,
+    False,
+    Arm(usize),
+    Back,
+    Break,
+    Continue,
+    Return,
+    TryOk,
+    TryErr,
+    Panic,
+}
+
+impl fmt::Display for EdgeKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EdgeKind::Goto => write!(f, "goto"),
+            EdgeKind::True => write!(f, "true"),
+            EdgeKind::False => write!(f, "false"),
+            EdgeKind::Arm(idx) => write!(f, "arm {}", idx),
+            EdgeKind::Back => write!(f, "back"),
+            EdgeKind::Break => write!(f, "break"),
+            EdgeKind::Continue => write!(f, "continue"),
+            EdgeKind::Return => write!(f, "return"),
+            EdgeKind::TryOk => write!(f, "ok"),
+            EdgeKind::TryErr => write!(f, "err"),
+            EdgeKind::Panic => write!(f, "panic"),
+        }
+    }
+}
+
+impl Cfg {
+    pub fn to_dot(&self) -> String {
+        let mut dot = format!("digraph \"{}\" {{\n    node [shape=box];\n", escape(&self.name));
+        for block in &self.blocks {
+            let name = match block.id {
+                ENTRY => "entry".to_string(),
+                EXIT => "exit".to_string(),
+                id => format!("bb{}", id),
+            };
+            let mut label = vec![name];
+            label.extend(block.spans.iter()
+                .map(|(start_l, start, end_l, end)| format!("{}:{}-{}:{}", start_l, start, end_l, end)));
+            dot += &format!("    b{} [label=\"{}\"];\n", block.id, label.join("\\n"));
+        }
+        for edge in &self.edges {
+            let label = match (&edge.kind, &edge.cond) {
+                (EdgeKind::Goto, _) => String::new(),
+                (EdgeKind::Arm(_), Some((_, pat))) => format!("{}: {}", edge.kind, escape(pat)),
+                (kind, _) => kind.to_string(),
+            };
+            dot += &format!("    b{} -> b{} [label=\"{}\"];\n", edge.from, edge.to, label);
+        }
+        dot += "}\n";
+        dot
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn span_of<T: Spanned>(node: &T) -> Span {
+    let span = node.span();
+    (span.start().line, span.start().column, span.end().line, span.end().column)
+}
+
+fn decision<T: ToTokens>(node: &T) -> Option<Decision> {
+    Some((span_of(node), node.to_token_stream().to_string()))
+}
+
+// Builds one CFG per `fn` (free functions, methods and nested functions) in the file.
+pub fn build_all(file: &syn::File) -> Vec<Cfg> {
+    let mut collector = FnCollector { path: Vec::new(), cfgs: Vec::new() };
+    collector.visit_file(file);
+    collector.cfgs
+}
+
+pub fn build(name: String, sig: &syn::Signature, body: &Block) -> Cfg {
+    let mut builder = Builder { blocks: Vec::new(), edges: Vec::new(), loops: Vec::new() };
+    builder.new_block();
+    builder.new_block();
+    builder.push_span(ENTRY, span_of(sig));
+
+    if let Some(end) = builder.block(body, ENTRY) {
+        builder.edge(end, EXIT, EdgeKind::Goto, None);
+    }
+
+    let mut cfg = Cfg { name, span: span_of(sig), blocks: builder.blocks, edges: builder.edges };
+    skip_empty_blocks(&mut cfg);
+    prune_unreachable(&mut cfg);
+    cfg
+}
+
+struct FnCollector {
+    path: Vec<String>,
+    cfgs: Vec<Cfg>,
+}
+
+impl FnCollector {
+    fn name(&self, ident: &syn::Ident) -> String {
+        let mut path = self.path.clone();
+        path.push(ident.to_string());
+        path.join("::")
+    }
+}
+
+impl<'ast> Visit<'ast> for FnCollector {
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        self.path.push(i.ident.to_string());
+        visit::visit_item_mod(self, i);
+        self.path.pop();
+    }
+
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        self.path.push(i.self_ty.to_token_stream().to_string());
+        visit::visit_item_impl(self, i);
+        self.path.pop();
+    }
+
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        self.cfgs.push(build(self.name(&i.sig.ident), &i.sig, &i.block));
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        self.cfgs.push(build(self.name(&i.sig.ident), &i.sig, &i.block));
+        visit::visit_impl_item_fn(self, i);
+    }
+}
+
+struct LoopCtx {
+    label: Option<String>,
+    head: usize,
+    exit: usize,
+}
+
+struct Builder {
+    blocks: Vec<BasicBlock>,
+    edges: Vec<Edge>,
+    loops: Vec<LoopCtx>,
+}
+
+// Each method takes the block control is in and returns the block it continues
+// in afterwards, or None when control never falls through (return, break, ...).
+impl Builder {
+    fn new_block(&mut self) -> usize {
+        let id = self.blocks.len();
+        self.blocks.push(BasicBlock { id, spans: Vec::new() });
+        id
+    }
+
+    fn edge(&mut self, from: usize, to: usize, kind: EdgeKind, cond: Option<Decision>) {
+        self.edges.push(Edge { from, to, kind, cond });
+    }
+
+    fn push_span(&mut self, block: usize, span: Span) {
+        let spans = &mut self.blocks[block].spans;
+        if !spans.iter().any(|s| contains(s, &span)) {
+            spans.push(span);
+        }
+    }
+
+    fn join(&mut self, ends: Vec<(usize, EdgeKind, Option<Decision>)>) -> Option<usize> {
+        if ends.is_empty() {
+            return None;
+        }
+        let join = self.new_block();
+        for (from, kind, cond) in ends {
+            self.edge(from, join, kind, cond);
+        }
+        Some(join)
+    }
+
+    fn block(&mut self, b: &Block, cur: usize) -> Option<usize> {
+        let mut cur = cur;
+        for s in &b.stmts {
+            cur = self.stmt(s, cur)?;
+        }
+        Some(cur)
+    }
+
+    fn stmt(&mut self, s: &Stmt, cur: usize) -> Option<usize> {
+        // a loop header is evaluated in the loop head, and nested items have CFGs of their own
+        let owns_code = !matches!(s, Stmt::Expr(Expr::While(_) | Expr::ForLoop(_) | Expr::Loop(_), _) | Stmt::Item(_));
+        if let Some(span) = crate::stmt_span(s).filter(|_| owns_code) {
+            self.push_span(cur, span);
+        }
+        match s {
+            Stmt::Local(local) => match &local.init {
+                Some(init) => {
+                    let cur = self.expr(&init.expr, cur)?;
+                    match &init.diverge {
+                        Some((_, diverge)) => {
+                            let else_blk = self.new_block();
+                            self.edge(cur, else_blk, EdgeKind::False, decision(&local.pat));
+                            self.expr(diverge, else_blk);
+                            let then_blk = self.new_block();
+                            self.edge(cur, then_blk, EdgeKind::True, decision(&local.pat));
+                            Some(then_blk)
+                        }
+                        None => Some(cur),
+                    }
+                }
+                None => Some(cur),
+            },
+            Stmt::Expr(e, _) => self.expr(e, cur),
+            Stmt::Macro(m) if is_diverging(&m.mac) => {
+                self.edge(cur, EXIT, EdgeKind::Panic, None);
+                None
+            }
+            Stmt::Macro(_) | Stmt::Item(_) => Some(cur),
+        }
+    }
+
+    fn expr(&mut self, e: &Expr, cur: usize) -> Option<usize> {
+        match e {
+            Expr::If(i) => {
+                let cur = self.expr(&i.cond, cur)?;
+                self.push_span(cur, span_of(&i.cond));
+
+                let mut ends = Vec::new();
+                let then_blk = self.new_block();
+                self.edge(cur, then_blk, EdgeKind::True, decision(&i.cond));
+                if let Some(end) = self.block(&i.then_branch, then_blk) {
+                    ends.push((end, EdgeKind::Goto, None));
+                }
+                match &i.else_branch {
+                    Some((_, else_expr)) => {
+                        let else_blk = self.new_block();
+                        self.edge(cur, else_blk, EdgeKind::False, decision(&i.cond));
+                        if let Some(end) = self.expr(else_expr, else_blk) {
+                            ends.push((end, EdgeKind::Goto, None));
+                        }
+                    }
+                    None => ends.push((cur, EdgeKind::False, decision(&i.cond))),
+                }
+                self.join(ends)
+            }
+            Expr::Match(m) => {
+                let cur = self.expr(&m.expr, cur)?;
+                self.push_span(cur, span_of(&m.expr));
+
+                let mut ends = Vec::new();
+                for (idx, arm) in m.arms.iter().enumerate() {
+                    let arm_blk = self.new_block();
+                    self.edge(cur, arm_blk, EdgeKind::Arm(idx), decision(&arm.pat));
+                    let mut arm_cur = Some(arm_blk);
+                    if let Some((_, guard)) = &arm.guard {
+                        self.push_span(arm_blk, span_of(guard));
+                        arm_cur = self.expr(guard, arm_blk);
+                    }
+                    if let Some(arm_cur) = arm_cur {
+                        self.push_span(arm_cur, span_of(&arm.body));
+                        if let Some(end) = self.expr(&arm.body, arm_cur) {
+                            ends.push((end, EdgeKind::Goto, None));
+                        }
+                    }
+                }
+                self.join(ends)
+            }
+            Expr::While(w) => {
+                let head = self.new_block();
+                self.edge(cur, head, EdgeKind::Goto, None);
+                self.push_span(head, span_of(&w.cond));
+                let head_end = self.expr(&w.cond, head)?;
+
+                let body = self.new_block();
+                let exit = self.new_block();
+                self.edge(head_end, body, EdgeKind::True, decision(&w.cond));
+                self.edge(head_end, exit, EdgeKind::False, decision(&w.cond));
+                self.loop_body(w.label.as_ref(), head, exit, &w.body, body);
+                Some(exit)
+            }
+            Expr::ForLoop(f) => {
+                let cur = self.expr(&f.expr, cur)?;
+                let head = self.new_block();
+                self.edge(cur, head, EdgeKind::Goto, None);
+                let header = (span_of(&f.pat).0, span_of(&f.pat).1, span_of(&f.expr).2, span_of(&f.expr).3);
+                self.push_span(head, header);
+                let cond = Some((header, format!("{} in {}", f.pat.to_token_stream(), f.expr.to_token_stream())));
+
+                let body = self.new_block();
+                let exit = self.new_block();
+                self.edge(head, body, EdgeKind::True, cond.clone());
+                self.edge(head, exit, EdgeKind::False, cond);
+                self.loop_body(f.label.as_ref(), head, exit, &f.body, body);
+                Some(exit)
+            }
+            Expr::Loop(l) => {
+                let head = self.new_block();
+                self.edge(cur, head, EdgeKind::Goto, None);
+                let exit = self.new_block();
+                self.loop_body(l.label.as_ref(), head, exit, &l.body, head);
+                if self.edges.iter().any(|e| e.to == exit) { Some(exit) } else { None }
+            }
+            Expr::Block(b) => self.block(&b.block, cur),
+            Expr::Unsafe(u) => self.block(&u.block, cur),
+            Expr::Return(r) => {
+                let cur = match &r.expr {
+                    Some(value) => self.expr(value, cur)?,
+                    None => cur,
+                };
+                self.edge(cur, EXIT, EdgeKind::Return, None);
+                None
+            }
+            Expr::Break(b) => {
+                let cur = match &b.expr {
+                    Some(value) => self.expr(value, cur)?,
+                    None => cur,
+                };
+                if let Some(target) = self.find_loop(b.label.as_ref()).map(|l| l.exit) {
+                    self.edge(cur, target, EdgeKind::Break, None);
+                }
+                None
+            }
+            Expr::Continue(c) => {
+                if let Some(target) = self.find_loop(c.label.as_ref()).map(|l| l.head) {
+                    self.edge(cur, target, EdgeKind::Continue, None);
+                }
+                None
+            }
+            Expr::Try(t) => {
+                let cur = self.expr(&t.expr, cur)?;
+                let ok = self.new_block();
+                self.edge(cur, ok, EdgeKind::TryOk, decision(&t.expr));
+                self.edge(cur, EXIT, EdgeKind::TryErr, decision(&t.expr));
+                Some(ok)
+            }
+            Expr::Macro(m) if is_diverging(&m.mac) => {
+                self.edge(cur, EXIT, EdgeKind::Panic, None);
+                None
+            }
+            Expr::Closure(_) => Some(cur),
+            _ => {
+                // sub-expressions are evaluated in source order
+                let mut nested = Nested { found: Vec::new() };
+                visit::visit_expr(&mut nested, e);
+                let mut cur = cur;
+                for sub in nested.found {
+                    cur = self.expr(sub, cur)?;
+                }
+                Some(cur)
+            }
+        }
+    }
+
+    fn loop_body(&mut self, label: Option<&syn::Label>, head: usize, exit: usize, body: &Block, body_blk: usize) {
+        self.loops.push(LoopCtx { label: label.map(|l| l.name.ident.to_string()), head, exit });
+        if let Some(end) = self.block(body, body_blk) {
+            self.edge(end, head, EdgeKind::Back, None);
+        }
+        self.loops.pop();
+    }
+
+    fn find_loop(&self, label: Option<&syn::Lifetime>) -> Option<&LoopCtx> {
+        match label {
+            Some(label) => self.loops.iter().rev().find(|l| l.label.as_deref() == Some(&label.ident.to_string())),
+            None => self.loops.last(),
+        }
+    }
+}
+
+// Collects the outermost control-flow sub-expressions, without entering closures or items.
+struct Nested<'ast> {
+    found: Vec<&'ast Expr>,
+}
+
+impl<'ast> Visit<'ast> for Nested<'ast> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        match e {
+            Expr::If(_) | Expr::Match(_) | Expr::While(_) | Expr::ForLoop(_) | Expr::Loop(_)
+            | Expr::Block(_) | Expr::Unsafe(_) | Expr::Return(_) | Expr::Break(_)
+            | Expr::Continue(_) | Expr::Try(_) => self.found.push(e),
+            Expr::Macro(m) if is_diverging(&m.mac) => self.found.push(e),
+            Expr::Closure(_) | Expr::Async(_) => {}
+            _ => visit::visit_expr(self, e),
+        }
+    }
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+}
+
+fn is_diverging(mac: &syn::Macro) -> bool {
+    mac.path.segments.last().is_some_and(|seg| {
+        matches!(seg.ident.to_string().as_str(), "panic" | "unreachable" | "todo" | "unimplemented")
+    })
+}
+
+pub fn contains(outer: &Span, inner: &Span) -> bool {
+    (outer.0, outer.1) <= (inner.0, inner.1) && (inner.2, inner.3) <= (outer.2, outer.3)
+}
+
+// Join blocks without code of their own only forward control; their incoming
+// edges are redirected to the successor, leaving the block unreachable.
+fn skip_empty_blocks(cfg: &mut Cfg) {
+    for block in cfg.blocks.iter().filter(|b| b.id != ENTRY && b.id != EXIT && b.spans.is_empty()) {
+        let outgoing: Vec<&Edge> = cfg.edges.iter().filter(|e| e.from == block.id).collect();
+        if let [Edge { to, kind: EdgeKind::Goto, .. }] = outgoing[..] {
+            let target = *to;
+            if target == block.id {
+                continue;
+            }
+            for edge in cfg.edges.iter_mut().filter(|e| e.to == block.id) {
+                edge.to = target;
+            }
+        }
+    }
+}
+
+// Drops blocks that cannot be reached from the entry (e.g. the exit of a `loop`
+// without `break`) and renumbers the rest. The exit block is always kept.
+fn prune_unreachable(cfg: &mut Cfg) {
+    let mut reachable = vec![false; cfg.blocks.len()];
+    let mut queue = VecDeque::from([ENTRY]);
+    reachable[ENTRY] = true;
+    reachable[EXIT] = true;
+    while let Some(block) = queue.pop_front() {
+        for edge in cfg.edges.iter().filter(|e| e.from == block) {
+            if !reachable[edge.to] {
+                reachable[edge.to] = true;
+                queue.push_back(edge.to);
+            }
+        }
+    }
+
+    let mut ids = BTreeMap::new();
+    cfg.blocks.retain(|b| reachable[b.id]);
+    for (new_id, block) in cfg.blocks.iter_mut().enumerate() {
+        ids.insert(block.id, new_id);
+        block.id = new_id;
+    }
+    cfg.edges.retain(|e| reachable[e.from]);
+    for edge in cfg.edges.iter_mut() {
+        edge.from = ids[&edge.from];
+        edge.to = ids[&edge.to];
+    }
+}
@@ -0,0 +1,231 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::process::Command;
+use syn::visit::{self, Visit};
+
+use crate::cfg::span_of;
+use crate::{snippet, Coverage, Span};
+
+// What a diff changes in the old version of a file, the one stored coverage was
+// recorded against: lines removed or rewritten, and lines new code was
+// inserted after. `added` holds the lines of the new version with new or
+// rewritten code.
+#[derive(Default)]
+pub struct Changes {
+    pub modified: BTreeSet<usize>,
+    pub inserted_after: BTreeSet<usize>,
+    pub added: BTreeSet<usize>,
+}
+
+// Reads the changes to `path` out of a unified diff, with or without context.
+// Blank lines and comments change no behavior and are left out.
+pub fn parse(diff: &str, path: &str) -> Changes {
+    let mut changes = Changes::default();
+    let mut current = false;
+    let (mut old_line, mut new_line, mut old_left, mut new_left) = (0, 0, 0, 0);
+    // the lines removed since the last context line
+    let mut removed: Vec<(usize, &str)> = Vec::new();
+    let lines: Vec<&str> = diff.lines().collect();
+    for (idx, line) in lines.iter().enumerate() {
+        if old_left > 0 || new_left > 0 {
+            match line.chars().next() {
+                Some('-') => {
+                    if is_code(&line[1..]) {
+                        changes.modified.insert(old_line);
+                    }
+                    removed.push((old_line, &line[1..]));
+                    old_line += 1;
+                    old_left -= 1;
+                }
+                Some('+') => {
+                    // a line added back unchanged (e.g. only its final newline
+                    // differs) is no change; other code replacing removed lines
+                    // rewrites the last of them
+                    if let Some(idx) = removed.iter().position(|(_, text)| *text == &line[1..]) {
+                        changes.modified.remove(&removed.remove(idx).0);
+                    } else if is_code(&line[1..]) {
+                        changes.added.insert(new_line);
+                        if removed.is_empty() {
+                            changes.inserted_after.insert(old_line - 1);
+                        } else {
+                            changes.modified.insert(old_line - 1);
+                        }
+                    }
+                    new_line += 1;
+                    new_left -= 1;
+                }
+                // `\ No newline at end of file`
+                Some('\\') => {}
+                _ => {
+                    old_line += 1;
+                    new_line += 1;
+                    old_left -= 1;
+                    new_left -= 1;
+                    removed.clear();
+                }
+            }
+        } else if let (Some(old), Some(new)) = (line.strip_prefix("--- "), lines.get(idx + 1).and_then(|l| l.strip_prefix("+++ "))) {
+            // the file may be either side, e.g. new files come from /dev/null
+            current = [(old, "a/"), (new, "b/")].iter().any(|(side, prefix)| {
+                let side = side.split('\t').next().unwrap_or(side);
+                same_file(side.strip_prefix(prefix).unwrap_or(side), path)
+            });
+        } else if let Some(hunk) = line.strip_prefix("@@ -").filter(|_| current) {
+            let mut ranges = hunk.split_whitespace();
+            let (old_start, old_count) = range(ranges.next().unwrap_or(""));
+            let (new_start, new_count) = range(ranges.next().unwrap_or("").trim_start_matches('+'));
+            // an empty range names the line the hunk goes after
+            old_line = if old_count == 0 { old_start + 1 } else { old_start };
+            new_line = if new_count == 0 { new_start + 1 } else { new_start };
+            (old_left, new_left) = (old_count, new_count);
+            removed.clear();
+        }
+    }
+    changes
+}
+
+fn is_code(line: &str) -> bool {
+    let line = line.trim();
+    !line.is_empty() && !line.starts_with("//")
+}
+
+// `start,count` of a hunk header, the count defaulting to one line.
+fn range(text: &str) -> (usize, usize) {
+    match text.split_once(',') {
+        Some((start, count)) => (start.parse().unwrap_or(0), count.parse().unwrap_or(0)),
+        None => (text.parse().unwrap_or(0), 1),
+    }
+}
+
+// Diff paths are relative to the repository or as given to `git diff`.
+fn same_file(diff_path: &str, path: &str) -> bool {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    Path::new(diff_path).ends_with(path) || Path::new(path).ends_with(diff_path)
+}
+
+fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git").args(args).current_dir(dir).output().expect("Something went wrong running git");
+    // `git diff --no-index` exits with 1 when the trees differ
+    if !output.status.success() && output.stdout.is_empty() {
+        panic!("git {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+    }
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+// The file as of `rev` and the diff from there to the working tree.
+pub fn from_git(path: &str, rev: &str) -> (String, String) {
+    let file = Path::new(path);
+    let dir = file.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let name = file.file_name().expect("Something went wrong reading the file name").to_string_lossy();
+    let source = git(dir, &["show", &format!("{}:./{}", rev, name)]);
+    let diff = git(dir, &["diff", "-U0", rev, "--", &name]);
+    (source, diff)
+}
+
+// The diff between two files or source trees.
+pub fn from_trees(old: &str, new: &str) -> String {
+    git(Path::new("."), &["diff", "--no-index", "-U0", old, new])
+}
+
+// The tests of the file, by name and span.
+struct TestFns(Vec<(String, Span)>);
+
+impl<'ast> Visit<'ast> for TestFns {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        if i.attrs.iter().any(|attr| attr.path().is_ident("test")) {
+            self.0.push((i.sig.ident.to_string(), span_of(i)));
+        }
+        visit::visit_item_fn(self, i);
+    }
+}
+
+fn lines_of(span: &Span) -> std::ops::RangeInclusive<usize> {
+    span.0..=span.2
+}
+
+// The innermost span holding all of `lines`.
+fn innermost<'a, T>(items: impl Iterator<Item = (T, &'a Span)>, lines: &[usize]) -> Option<(T, &'a Span)> {
+    items.filter(|(_, span)| lines.iter().all(|line| lines_of(span).contains(line)))
+        .min_by_key(|(_, span)| span.2 - span.0)
+}
+
+fn format_lines(lines: &BTreeSet<usize>) -> String {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for &line in lines {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == line => *end = line,
+            _ => ranges.push((line, line)),
+        }
+    }
+    ranges.iter().map(|(start, end)| if start == end { start.to_string() } else { format!("{}-{}", start, end) }).collect::<Vec<_>>().join(", ")
+}
+
+// Maps the changes onto the statements and functions found by
+// `CoverageVisitor` and prints the tests whose traces reach changed code. A
+// change outside any statement falls back to the tests running its lines, then
+// to its function; one outside any function (a type, a constant, ...) affects
+// every test. Code inserted between items is new and reached by no stored
+// trace.
+pub fn report(source: &str, file: &syn::File, coverage: &Coverage, traces: &[(String, Vec<usize>)], changes: &Changes) {
+    let mut tests = TestFns(Vec::new());
+    tests.visit_file(file);
+    let executed: BTreeSet<usize> = traces.iter().flat_map(|(_, trace)| trace.iter().copied()).collect();
+
+    let mut changed_tests = BTreeSet::new();
+    let mut statements: BTreeSet<Span> = BTreeSet::new();
+    let mut lines_run = BTreeSet::new();
+    let mut functions: BTreeSet<(String, Span)> = BTreeSet::new();
+    let mut outside = BTreeSet::new();
+    let sites = changes.modified.iter().map(|line| vec![*line])
+        .chain(changes.inserted_after.iter().map(|line| vec![*line, line + 1]));
+    for lines in sites {
+        let function = innermost(coverage.func_cov.values().map(|(name, span)| (name, span)), &lines);
+        if let Some((name, _)) = innermost(tests.0.iter().map(|(name, span)| (name, span)), &lines) {
+            changed_tests.insert(name.clone());
+        } else if let Some((_, span)) = innermost(coverage.stmt_cov.values().map(|span| ((), span)), &lines) {
+            statements.insert(*span);
+        } else if let Some(line) = function.and(lines.iter().rev().find(|line| executed.contains(line))) {
+            // other lines the traces record, such as `else if` conditions, or
+            // the code next to an insertion
+            lines_run.insert(*line);
+        } else if let Some((name, span)) = function {
+            functions.insert((name.clone(), *span));
+        } else if lines.len() == 1 {
+            outside.insert(lines[0]);
+        }
+    }
+
+    let affected: Vec<&String> = traces.iter()
+        .filter(|(name, trace)| {
+            !outside.is_empty()
+                || changed_tests.iter().any(|test| name == test || name.ends_with(&format!("::{}", test)))
+                || statements.iter().any(|span| trace.contains(&span.0))
+                || lines_run.iter().any(|line| trace.contains(line))
+                || functions.iter().any(|(_, span)| trace.iter().any(|line| lines_of(span).contains(line)))
+        })
+        .map(|(name, _)| name)
+        .collect();
+
+    println!("Changed lines: {}", format_lines(&changes.modified));
+    println!("Code inserted after lines: {}", format_lines(&changes.inserted_after));
+    println!("Changed code:");
+    for span in &statements {
+        println!("- statement {}:{}-{}:{} `{}`", span.0, span.1, span.2, span.3, snippet(source, span));
+    }
+    for line in &lines_run {
+        println!("- line {} `{}`", line, source.lines().nth(line - 1).unwrap_or("").trim());
+    }
+    for (name, span) in &functions {
+        println!("- fn {} ({})", name, span.0);
+    }
+    for name in &changed_tests {
+        println!("- test {}", name);
+    }
+    if !outside.is_empty() {
+        println!("- outside functions at lines {}: every test is affected", format_lines(&outside));
+    }
+    println!("Affected tests ({} of {}):", affected.len(), traces.len());
+    for name in affected {
+        println!("- {}", name);
+    }
+}
@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::summary::{Branch, BranchKind, FileCoverage};
+
+const DOCTYPE: &str = "<!DOCTYPE coverage SYSTEM \"http://cobertura.sourceforge.net/xml/coverage-04.dtd\">";
+
+// A Cobertura report (coverage-04 DTD): one package per module holding the
+// files, one class per file, one method per function. Lines carry their hits
+// and, for decisions, the share of arms taken as `condition-coverage`.
+pub fn report(files: &[FileCoverage]) -> String {
+    let mut packages: BTreeMap<String, Vec<&FileCoverage>> = BTreeMap::new();
+    for file in files {
+        let module = module_path(&file.path);
+        let package = module.rsplit_once("::").map_or(module.clone(), |(parent, _)| parent.to_string());
+        packages.entry(package).or_default().push(file);
+    }
+
+    let all: Vec<&FileCoverage> = files.iter().collect();
+    let (lines_covered, lines_valid, branches_covered, branches_valid) = totals(&all);
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_millis());
+    let mut xml = format!("<?xml version=\"1.0\" ?>\n{}\n", DOCTYPE);
+    xml += &format!("<coverage line-rate=\"{}\" branch-rate=\"{}\" lines-covered=\"{}\" lines-valid=\"{}\" branches-covered=\"{}\" \
+                     branches-valid=\"{}\" complexity=\"{}\" version=\"{}\" timestamp=\"{}\">\n",
+                    rate(lines_covered, lines_valid), rate(branches_covered, branches_valid), lines_covered, lines_valid,
+                    branches_covered, branches_valid, complexity(&all), env!("CARGO_PKG_VERSION"), timestamp);
+    xml += "  <sources>\n    <source>.</source>\n  </sources>\n  <packages>\n";
+    for (name, files) in &packages {
+        let (lines_covered, lines_valid, branches_covered, branches_valid) = totals(files);
+        xml += &format!("    <package name=\"{}\" line-rate=\"{}\" branch-rate=\"{}\" complexity=\"{}\">\n      <classes>\n",
+                        escape(name), rate(lines_covered, lines_valid), rate(branches_covered, branches_valid), complexity(files));
+        for file in files {
+            xml += &class(file);
+        }
+        xml += "      </classes>\n    </package>\n";
+    }
+    xml += "  </packages>\n</coverage>\n";
+    xml
+}
+
+fn class(file: &FileCoverage) -> String {
+    let (lines_covered, lines_valid, branches_covered, branches_valid) = totals(&[file]);
+    let mut xml = format!("        <class name=\"{}\" filename=\"{}\" line-rate=\"{}\" branch-rate=\"{}\" complexity=\"{}\">\n",
+                          escape(&module_path(&file.path)), escape(&file.path), rate(lines_covered, lines_valid),
+                          rate(branches_covered, branches_valid), complexity(&[file]));
+    xml += "          <methods>\n";
+    for function in &file.functions {
+        // the lines of the function, leaving out those of functions nested in it
+        let owns = |line: usize| (function.span.0..=function.span.2).contains(&line)
+            && !file.functions.iter().any(|inner| inner.span != function.span
+                && (function.span.0..=function.span.2).contains(&inner.span.0)
+                && (inner.span.0..=inner.span.2).contains(&line));
+        let lines: Vec<(&usize, &u64)> = file.lines.iter().filter(|(line, _)| owns(**line)).collect();
+        let branches: Vec<&Branch> = file.branches.iter().filter(|branch| owns(branch.line)).collect();
+        let arms: usize = branches.iter().map(|branch| branch.arms.len()).sum();
+        let taken: usize = branches.iter().map(|branch| branch.covered()).sum();
+        xml += &format!("            <method name=\"{}\" signature=\"{}\" line-rate=\"{}\" branch-rate=\"{}\" complexity=\"{}\">\n",
+                        escape(&function.name), escape(&file.signature(function)),
+                        rate(lines.iter().filter(|(_, count)| **count > 0).count(), lines.len()), rate(taken, arms),
+                        function.complexity);
+        xml += "              <lines>\n";
+        for (line, count) in lines {
+            xml += &line_element(file, *line, *count, "                ");
+        }
+        xml += "              </lines>\n            </method>\n";
+    }
+    xml += "          </methods>\n          <lines>\n";
+    for (line, count) in &file.lines {
+        xml += &line_element(file, *line, *count, "            ");
+    }
+    xml += "          </lines>\n        </class>\n";
+    xml
+}
+
+fn line_element(file: &FileCoverage, line: usize, count: u64, indent: &str) -> String {
+    let branches: Vec<&Branch> = file.branches.iter().filter(|branch| branch.line == line).collect();
+    if branches.is_empty() {
+        return format!("{}<line number=\"{}\" hits=\"{}\" branch=\"false\"/>\n", indent, line, count);
+    }
+    let arms: usize = branches.iter().map(|branch| branch.arms.len()).sum();
+    let taken: usize = branches.iter().map(|branch| branch.covered()).sum();
+    let mut xml = format!("{}<line number=\"{}\" hits=\"{}\" branch=\"true\" condition-coverage=\"{}% ({}/{})\">\n",
+                          indent, line, count, percent(taken, arms), taken, arms);
+    xml += &format!("{}  <conditions>\n", indent);
+    for (number, branch) in branches.iter().enumerate() {
+        let kind = if branch.kind == BranchKind::Switch { "switch" } else { "jump" };
+        xml += &format!("{}    <condition number=\"{}\" type=\"{}\" coverage=\"{}%\"/>\n",
+                        indent, number, kind, percent(branch.covered(), branch.arms.len()));
+    }
+    xml += &format!("{}  </conditions>\n{}</line>\n", indent, indent);
+    xml
+}
+
+// The module a file defines, from its path below `src/`: `src/a/b.rs` is
+// `crate::a::b`, `src/lib.rs` and `src/a/mod.rs` the module of their directory.
+pub fn module_path(path: &str) -> String {
+    let components: Vec<String> = Path::new(path).with_extension("").components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+    let below_src = components.iter().rposition(|c| c == "src").map_or(&components[components.len().saturating_sub(1)..], |idx| &components[idx + 1..]);
+    let mut module = vec!["crate".to_string()];
+    module.extend(below_src.iter().filter(|c| !matches!(c.as_str(), "lib" | "main" | "mod")).cloned());
+    module.join("::")
+}
+
+fn totals(files: &[&FileCoverage]) -> (usize, usize, usize, usize) {
+    files.iter().fold((0, 0, 0, 0), |(lc, lv, bc, bv), file| {
+        (lc + file.lines_covered(), lv + file.lines.len(), bc + file.arms_covered(), bv + file.arms())
+    })
+}
+
+fn complexity(files: &[&FileCoverage]) -> usize {
+    files.iter().flat_map(|file| file.functions.iter().map(|function| function.complexity)).sum()
+}
+
+// Rates are fractions; nothing to cover counts as fully covered.
+fn rate(covered: usize, total: usize) -> String {
+    if total == 0 { "1".to_string() } else { format!("{:.4}", covered as f64 / total as f64) }
+}
+
+fn percent(covered: usize, total: usize) -> usize {
+    (covered * 100).checked_div(total).unwrap_or(100)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
@@ -0,0 +1,49 @@
+use crate::cfg::contains;
+use crate::hits::LineHits;
+use crate::{Coverage, Span};
+
+// CRAP = complexity² × (1 − coverage)³ + complexity
+pub fn crap(complexity: usize, coverage: f64) -> f64 {
+    let complexity = complexity as f64;
+    complexity * complexity * (1.0 - coverage).powi(3) + complexity
+}
+
+// Statements of a function, leaving out those of functions nested in it.
+pub fn func_stmts<'a>(coverage: &'a Coverage, func: &Span) -> Vec<&'a Span> {
+    coverage.stmt_cov.values()
+        .filter(|stmt| contains(func, stmt))
+        .filter(|stmt| !coverage.func_cov.values().any(|(_, inner)| inner != func && contains(func, inner) && contains(inner, stmt)))
+        .collect()
+}
+
+pub fn report(coverage: &Coverage, hits: Option<&LineHits>) {
+    let mut rows = Vec::new();
+    for (idx, (name, span)) in &coverage.func_cov {
+        let (cyclomatic, cognitive) = coverage.complexity.get(idx).copied().unwrap_or((1, 0));
+        let stmt = hits.map(|hits| {
+            let stmts = func_stmts(coverage, span);
+            let covered = stmts.iter().filter(|stmt| hits.span_count(stmt) > 0).count();
+            (covered, stmts.len())
+        });
+        let ratio = stmt.map(|(covered, total)| if total == 0 { 1.0 } else { covered as f64 / total as f64 });
+        rows.push((name, cyclomatic, cognitive, stmt, ratio.map(|r| crap(cyclomatic, r))));
+    }
+
+    // riskiest first: by CRAP score when coverage is known, by complexity otherwise
+    rows.sort_by(|a, b| match (a.4, b.4) {
+        (Some(a), Some(b)) => b.total_cmp(&a),
+        _ => b.1.cmp(&a.1).then(b.2.cmp(&a.2)),
+    });
+
+    println!("Complexity:");
+    for (name, cyclomatic, cognitive, stmt, crap) in rows {
+        match (stmt, crap) {
+            (Some((covered, total)), Some(crap)) => {
+                let ratio = if total == 0 { 100.0 } else { covered as f64 / total as f64 * 100.0 };
+                println!("- {}: cyclomatic {}, cognitive {}, stmt {}/{} ({:.2}%), CRAP {:.2}",
+                         name, cyclomatic, cognitive, covered, total, ratio, crap);
+            }
+            _ => println!("- {}: cyclomatic {}, cognitive {}", name, cyclomatic, cognitive),
+        }
+    }
+}
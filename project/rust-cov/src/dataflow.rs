@@ -0,0 +1,439 @@
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{punctuated::Punctuated, visit::{self, Visit}, Block, Expr, Pat, Stmt, Token};
+
+use crate::cfg::span_of;
+use crate::{snippet, Span};
+
+// variable -> ids of the definitions reaching the current point
+type State = BTreeMap<String, BTreeSet<usize>>;
+
+pub struct Def {
+    pub var: String,
+    // the statement or expression performing the definition
+    pub site: Span,
+}
+
+pub struct Use {
+    pub var: String,
+    pub at: Span,
+    // the statement or condition containing the use
+    pub site: Span,
+}
+
+pub struct DefUse {
+    pub name: String,
+    pub sig_line: usize,
+    pub params: BTreeSet<String>,
+    pub defs: Vec<Def>,
+    pub uses: Vec<Use>,
+    pub pairs: BTreeSet<(usize, usize)>,
+}
+
+pub fn analyze_all(file: &syn::File) -> Vec<DefUse> {
+    let mut collector = FnCollector { results: Vec::new() };
+    collector.visit_file(file);
+    collector.results
+}
+
+struct FnCollector {
+    results: Vec<DefUse>,
+}
+
+impl<'ast> Visit<'ast> for FnCollector {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        self.results.push(analyze(&i.sig, &i.block));
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        self.results.push(analyze(&i.sig, &i.block));
+        visit::visit_impl_item_fn(self, i);
+    }
+}
+
+pub fn analyze(sig: &syn::Signature, body: &Block) -> DefUse {
+    let mut analysis = Analysis {
+        defs: Vec::new(),
+        uses: Vec::new(),
+        pairs: BTreeSet::new(),
+        locals: BTreeSet::new(),
+        site: span_of(sig),
+        loops: Vec::new(),
+    };
+
+    let mut state = State::new();
+    let mut params = BTreeSet::new();
+    for input in &sig.inputs {
+        match input {
+            syn::FnArg::Typed(arg) => {
+                for var in bindings(&arg.pat) {
+                    params.insert(var.clone());
+                    analysis.def(&mut state, var, span_of(arg));
+                }
+            }
+            syn::FnArg::Receiver(receiver) => {
+                params.insert("self".to_string());
+                analysis.def(&mut state, "self".to_string(), span_of(receiver));
+            }
+        }
+    }
+    analysis.block(body, state);
+
+    DefUse {
+        name: sig.ident.to_string(),
+        sig_line: span_of(sig).0,
+        params,
+        defs: analysis.defs,
+        uses: analysis.uses,
+        pairs: analysis.pairs,
+    }
+}
+
+fn bindings(pat: &Pat) -> Vec<String> {
+    struct Bindings(Vec<String>);
+    impl<'ast> Visit<'ast> for Bindings {
+        fn visit_pat_ident(&mut self, i: &'ast syn::PatIdent) {
+            self.0.push(i.ident.to_string());
+            visit::visit_pat_ident(self, i);
+        }
+    }
+    let mut found = Bindings(Vec::new());
+    found.visit_pat(pat);
+    found.0
+}
+
+fn merge(into: &mut State, other: State) {
+    for (var, defs) in other {
+        into.entry(var).or_default().extend(defs);
+    }
+}
+
+struct LoopStates {
+    breaks: State,
+    continues: State,
+}
+
+// Reaching definitions computed over the syntax tree: branches are analysed on
+// copies of the state and merged, loops are iterated until the state at their
+// head no longer grows. Code after `return` / `break` sees an empty state.
+struct Analysis {
+    defs: Vec<Def>,
+    uses: Vec<Use>,
+    pairs: BTreeSet<(usize, usize)>,
+    locals: BTreeSet<String>,
+    site: Span,
+    loops: Vec<LoopStates>,
+}
+
+impl Analysis {
+    fn def(&mut self, state: &mut State, var: String, site: Span) {
+        let id = match self.defs.iter().position(|d| d.var == var && d.site == site) {
+            Some(id) => id,
+            None => {
+                self.defs.push(Def { var: var.clone(), site });
+                self.defs.len() - 1
+            }
+        };
+        self.locals.insert(var.clone());
+        state.insert(var, BTreeSet::from([id]));
+    }
+
+    fn use_var(&mut self, state: &State, var: &str, at: Span) {
+        if !self.locals.contains(var) {
+            return;
+        }
+        let id = match self.uses.iter().position(|u| u.var == var && u.at == at) {
+            Some(id) => id,
+            None => {
+                self.uses.push(Use { var: var.to_string(), at, site: self.site });
+                self.uses.len() - 1
+            }
+        };
+        for def in state.get(var).into_iter().flatten() {
+            self.pairs.insert((*def, id));
+        }
+    }
+
+    fn with_site<T>(&mut self, site: Span, f: impl FnOnce(&mut Self) -> T) -> T {
+        let saved = std::mem::replace(&mut self.site, site);
+        let result = f(self);
+        self.site = saved;
+        result
+    }
+
+    fn block(&mut self, b: &Block, mut state: State) -> State {
+        for s in &b.stmts {
+            state = self.stmt(s, state);
+        }
+        state
+    }
+
+    fn stmt(&mut self, s: &Stmt, state: State) -> State {
+        let Some(site) = crate::stmt_span(s) else {
+            return match s {
+                Stmt::Expr(e, _) => self.expr(e, state),
+                _ => state,
+            };
+        };
+        self.with_site(site, |a| match s {
+            Stmt::Local(local) => {
+                let mut state = match &local.init {
+                    Some(init) => {
+                        let state = a.expr(&init.expr, state);
+                        match &init.diverge {
+                            Some((_, diverge)) => {
+                                a.expr(diverge, state.clone());
+                                state
+                            }
+                            None => state,
+                        }
+                    }
+                    None => state,
+                };
+                for var in bindings(&local.pat) {
+                    a.def(&mut state, var, site);
+                }
+                state
+            }
+            Stmt::Expr(e, _) => a.expr(e, state),
+            Stmt::Macro(m) => a.mac(&m.mac, state),
+            Stmt::Item(_) => state,
+        })
+    }
+
+    fn mac(&mut self, mac: &syn::Macro, mut state: State) -> State {
+        if let Ok(args) = mac.parse_body_with(Punctuated::<Expr, Token![,]>::parse_terminated) {
+            for arg in &args {
+                state = self.expr(arg, state);
+            }
+        }
+        state
+    }
+
+    fn expr(&mut self, e: &Expr, state: State) -> State {
+        match e {
+            Expr::Path(p) => {
+                if let Some(ident) = p.path.get_ident() {
+                    self.use_var(&state, &ident.to_string(), span_of(p));
+                }
+                state
+            }
+            Expr::Assign(a) => {
+                let mut state = self.expr(&a.right, state);
+                match a.left.as_ref() {
+                    Expr::Path(p) if p.path.get_ident().is_some() => {
+                        let var = p.path.get_ident().unwrap().to_string();
+                        if self.locals.contains(&var) {
+                            self.def(&mut state, var, span_of(a));
+                        }
+                        state
+                    }
+                    // `array[i] = x` or `s.f = x` read the base and the index
+                    left => self.expr(left, state),
+                }
+            }
+            Expr::Binary(b) if is_compound_assign(&b.op) => {
+                let state = self.expr(&b.right, state);
+                let mut state = self.expr(&b.left, state);
+                if let Expr::Path(p) = b.left.as_ref() {
+                    if let Some(ident) = p.path.get_ident() {
+                        self.def(&mut state, ident.to_string(), span_of(b));
+                    }
+                }
+                state
+            }
+            Expr::If(i) => {
+                let state = self.with_site(span_of(&i.cond), |a| a.expr(&i.cond, state));
+                let mut merged = self.block(&i.then_branch, state.clone());
+                match &i.else_branch {
+                    Some((_, else_expr)) => merge(&mut merged, self.expr(else_expr, state)),
+                    None => merge(&mut merged, state),
+                }
+                merged
+            }
+            Expr::Let(l) => {
+                let mut state = self.expr(&l.expr, state);
+                for var in bindings(&l.pat) {
+                    self.def(&mut state, var, span_of(l));
+                }
+                state
+            }
+            Expr::Match(m) => {
+                let state = self.with_site(span_of(&m.expr), |a| a.expr(&m.expr, state));
+                let mut merged = State::new();
+                for arm in &m.arms {
+                    let mut arm_state = state.clone();
+                    for var in bindings(&arm.pat) {
+                        self.def(&mut arm_state, var, span_of(&arm.pat));
+                    }
+                    if let Some((_, guard)) = &arm.guard {
+                        arm_state = self.with_site(span_of(guard), |a| a.expr(guard, arm_state));
+                    }
+                    let body_state = self.with_site(span_of(&arm.body), |a| a.expr(&arm.body, arm_state));
+                    merge(&mut merged, body_state);
+                }
+                merged
+            }
+            Expr::While(w) => {
+                let cond_site = span_of(&w.cond);
+                self.fixpoint(state, |a, head| {
+                    let after_cond = a.with_site(cond_site, |a| a.expr(&w.cond, head));
+                    let after_body = a.block(&w.body, after_cond.clone());
+                    (after_body, after_cond)
+                })
+            }
+            Expr::ForLoop(f) => {
+                let header = (span_of(&f.pat).0, span_of(&f.pat).1, span_of(&f.expr).2, span_of(&f.expr).3);
+                let state = self.with_site(header, |a| a.expr(&f.expr, state));
+                self.fixpoint(state, |a, head| {
+                    let mut body_state = head.clone();
+                    for var in bindings(&f.pat) {
+                        a.def(&mut body_state, var, header);
+                    }
+                    (a.block(&f.body, body_state), head)
+                })
+            }
+            Expr::Loop(l) => self.fixpoint(state, |a, head| (a.block(&l.body, head), State::new())),
+            Expr::Break(b) => {
+                let state = match &b.expr {
+                    Some(value) => self.expr(value, state),
+                    None => state,
+                };
+                if let Some(states) = self.loops.last_mut() {
+                    merge(&mut states.breaks, state);
+                }
+                State::new()
+            }
+            Expr::Continue(_) => {
+                if let Some(states) = self.loops.last_mut() {
+                    merge(&mut states.continues, state);
+                }
+                State::new()
+            }
+            Expr::Return(r) => {
+                if let Some(value) = &r.expr {
+                    self.expr(value, state);
+                }
+                State::new()
+            }
+            Expr::Block(b) => self.block(&b.block, state),
+            Expr::Unsafe(u) => self.block(&u.block, state),
+            Expr::Closure(c) => {
+                // captured variables are read where the closure is created
+                self.expr(&c.body, state.clone());
+                state
+            }
+            Expr::Macro(m) => self.mac(&m.mac, state),
+            _ => {
+                let mut children = Children(Vec::new());
+                visit::visit_expr(&mut children, e);
+                let mut state = state;
+                for child in children.0 {
+                    state = self.expr(child, state);
+                }
+                state
+            }
+        }
+    }
+
+    // `step` gets the state at the loop head and returns the state at the end of
+    // the body and the state leaving the loop normally (e.g. a false condition).
+    fn fixpoint<F>(&mut self, entry: State, mut step: F) -> State
+    where
+        F: FnMut(&mut Self, State) -> (State, State),
+    {
+        let mut head = entry.clone();
+        loop {
+            self.loops.push(LoopStates { breaks: State::new(), continues: State::new() });
+            let (body_end, mut exit) = step(self, head.clone());
+            let states = self.loops.pop().unwrap();
+
+            let mut next = entry.clone();
+            merge(&mut next, body_end);
+            merge(&mut next, states.continues);
+            if next == head {
+                merge(&mut exit, states.breaks);
+                return exit;
+            }
+            head = next;
+        }
+    }
+}
+
+fn is_compound_assign(op: &syn::BinOp) -> bool {
+    use syn::BinOp::*;
+    matches!(op, AddAssign(_) | SubAssign(_) | MulAssign(_) | DivAssign(_) | RemAssign(_)
+        | BitXorAssign(_) | BitAndAssign(_) | BitOrAssign(_) | ShlAssign(_) | ShrAssign(_))
+}
+
+// Direct sub-expressions of an expression, in source order.
+struct Children<'ast>(Vec<&'ast Expr>);
+
+impl<'ast> Visit<'ast> for Children<'ast> {
+    fn visit_expr(&mut self, e: &'ast Expr) {
+        self.0.push(e);
+    }
+
+    fn visit_item(&mut self, _: &'ast syn::Item) {}
+}
+
+pub struct DefUseCoverage {
+    pub covered: BTreeSet<(usize, usize)>,
+}
+
+// Replays a line trace: on every executed line the uses are paired with the
+// last executed definition of their variable, then the line's definitions run.
+pub fn exercised(du: &DefUse, traces: &[(String, Vec<usize>)]) -> DefUseCoverage {
+    let mut covered = BTreeSet::new();
+    for (_, trace) in traces {
+        let mut last_def: BTreeMap<&str, usize> = BTreeMap::new();
+        for &line in trace {
+            if line == du.sig_line {
+                last_def.clear();
+            }
+            for (use_id, u) in du.uses.iter().enumerate().filter(|(_, u)| u.at.0 == line) {
+                let def = last_def.get(u.var.as_str()).copied().or_else(|| {
+                    // parameters are defined on entry even if the trace omits the signature
+                    du.params.contains(&u.var).then(|| du.defs.iter().position(|d| d.var == u.var)).flatten()
+                });
+                if let Some(def) = def {
+                    if du.pairs.contains(&(def, use_id)) {
+                        covered.insert((def, use_id));
+                    }
+                }
+            }
+            for (def_id, d) in du.defs.iter().enumerate().filter(|(_, d)| d.site.0 == line) {
+                last_def.insert(&d.var, def_id);
+            }
+        }
+    }
+    DefUseCoverage { covered }
+}
+
+pub fn report(source: &str, results: &[DefUse], traces: &[(String, Vec<usize>)]) {
+    println!("Def-use coverage:");
+    for du in results {
+        let coverage = exercised(du, traces);
+        let defs_with_pairs: BTreeSet<usize> = du.pairs.iter().map(|(d, _)| *d).collect();
+        let defs_covered: BTreeSet<usize> = coverage.covered.iter().map(|(d, _)| *d).collect();
+
+        println!("- {}:", du.name);
+        println!("  - all-defs: {}/{} ({:.2}%)", defs_covered.len(), defs_with_pairs.len(),
+                 percent(defs_covered.len(), defs_with_pairs.len()));
+        println!("  - all-uses: {}/{} ({:.2}%)", coverage.covered.len(), du.pairs.len(),
+                 percent(coverage.covered.len(), du.pairs.len()));
+        for (def, use_id) in &du.pairs {
+            let (d, u) = (&du.defs[*def], &du.uses[*use_id]);
+            let marker = if coverage.covered.contains(&(*def, *use_id)) { '*' } else { '-' };
+            println!("    {} {}: `{}` ({}) → `{}` ({}:{})", marker, d.var,
+                     snippet(source, &d.site), d.site.0, snippet(source, &u.site), u.at.0, u.at.1);
+        }
+        for (_, d) in du.defs.iter().enumerate().filter(|(id, _)| !defs_with_pairs.contains(id)) {
+            println!("    - {}: `{}` ({}) is never used", d.var, snippet(source, &d.site), d.site.0);
+        }
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { covered as f64 / total as f64 * 100.0 }
+}
@@ -0,0 +1,142 @@
+use crate::cfg::{Cfg, Edge, EdgeKind, ENTRY, EXIT};
+use crate::hits::LineHits;
+
+pub struct EdgeCoverage<'a> {
+    pub cfg: &'a Cfg,
+    // execution count of every edge, indexed like `cfg.edges`
+    pub counts: Vec<u64>,
+}
+
+impl<'a> EdgeCoverage<'a> {
+    pub fn new(cfg: &'a Cfg, hits: &LineHits) -> Self {
+        Self { cfg, counts: edge_counts(cfg, hits) }
+    }
+
+    pub fn covered(&self) -> usize {
+        self.counts.iter().filter(|&&c| c > 0).count()
+    }
+
+    pub fn uncovered(&self) -> impl Iterator<Item = &Edge> {
+        self.cfg.edges.iter().zip(&self.counts).filter(|(_, &c)| c == 0).map(|(e, _)| e)
+    }
+}
+
+// Block counts are read from the line hits (the smallest count among the first
+// lines of the block's spans). Blocks without code of their own are unknown.
+pub fn edge_counts(cfg: &Cfg, hits: &LineHits) -> Vec<u64> {
+    let blocks: Vec<Option<u64>> = cfg.blocks.iter()
+        .map(|b| b.spans.iter().map(|span| hits.span_count(span)).min())
+        .collect();
+
+    if hits.has_counts() {
+        conservation(cfg, blocks)
+    } else {
+        reachability(cfg, blocks)
+    }
+}
+
+// With execution counts, edge counts follow from flow conservation: the count of
+// a block equals the sum of its incoming and of its outgoing edges. Edges the
+// equations leave open are taken when both of their ends ran.
+fn conservation(cfg: &Cfg, mut blocks: Vec<Option<u64>>) -> Vec<u64> {
+    let mut edges: Vec<Option<u64>> = vec![None; cfg.edges.len()];
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for (block, count) in blocks.iter_mut().enumerate() {
+            let ins: Vec<usize> = (0..edges.len()).filter(|&e| cfg.edges[e].to == block).collect();
+            let outs: Vec<usize> = (0..edges.len()).filter(|&e| cfg.edges[e].from == block).collect();
+
+            for (side, skip) in [(&ins, block == ENTRY), (&outs, block == EXIT)] {
+                if skip || side.is_empty() {
+                    continue;
+                }
+                let unknown: Vec<usize> = side.iter().copied().filter(|&e| edges[e].is_none()).collect();
+                let known: u64 = side.iter().filter_map(|&e| edges[e]).sum();
+                match (*count, unknown.as_slice()) {
+                    (Some(count), [e]) => {
+                        edges[*e] = Some(count.saturating_sub(known));
+                        changed = true;
+                    }
+                    (Some(0), unknown) if !unknown.is_empty() => {
+                        unknown.iter().for_each(|&e| edges[e] = Some(0));
+                        changed = true;
+                    }
+                    (None, []) => {
+                        *count = Some(known);
+                        changed = true;
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    cfg.edges.iter().zip(edges)
+        .map(|(edge, count)| count.unwrap_or_else(|| {
+            let ran = |b: usize| blocks[b].unwrap_or(0) > 0;
+            (ran(edge.from) && ran(edge.to)) as u64
+        }))
+        .collect()
+}
+
+// With plain covered lines, an edge is taken when both of its ends ran. A
+// block without code of its own ran when a predecessor with no other successor
+// did, and the edges into it count only from such a predecessor: a branch
+// cannot tell which of its arms led there.
+fn reachability(cfg: &Cfg, blocks: Vec<Option<u64>>) -> Vec<u64> {
+    let mut ran: Vec<bool> = blocks.iter().map(|count| count.unwrap_or(0) > 0).collect();
+    let single_successor = |b: usize| cfg.edges.iter().filter(|edge| edge.from == b).count() == 1;
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for edge in &cfg.edges {
+            if blocks[edge.to].is_none() && ran[edge.from] && !ran[edge.to] && single_successor(edge.from) {
+                ran[edge.to] = true;
+                changed = true;
+            }
+        }
+    }
+
+    cfg.edges.iter()
+        .map(|edge| (ran[edge.from] && ran[edge.to] && (blocks[edge.to].is_some() || single_successor(edge.from))) as u64)
+        .collect()
+}
+
+pub fn report(file_name: &str, coverages: &[EdgeCoverage]) {
+    let total: usize = coverages.iter().map(|c| c.counts.len()).sum();
+    let covered: usize = coverages.iter().map(|c| c.covered()).sum();
+    println!("Edge coverage: {}/{} ({:.2}%)", covered, total, percent(covered, total));
+
+    for coverage in coverages {
+        println!("- {}: {}/{} ({:.2}%)", coverage.cfg.name, coverage.covered(), coverage.counts.len(),
+                 percent(coverage.covered(), coverage.counts.len()));
+        for edge in coverage.uncovered() {
+            println!("  - {}", describe(file_name, coverage.cfg, edge));
+        }
+    }
+}
+
+pub fn describe(file_name: &str, cfg: &Cfg, edge: &Edge) -> String {
+    match &edge.cond {
+        Some((span, cond)) => format!("{}:{} `{}` → {} edge never taken", file_name, span.0, cond, edge.kind),
+        None => {
+            // locate plain edges by the last code of their source block, else by
+            // the code they lead to or the condition leading to their source
+            let entered_on = cfg.edges.iter().filter(|e| e.to == edge.from).find_map(|e| e.cond.as_ref().map(|(span, _)| span.0));
+            let line = cfg.blocks[edge.from].spans.last().map(|span| span.2)
+                .or_else(|| cfg.blocks[edge.to].spans.first().map(|span| span.0))
+                .or(entered_on)
+                .unwrap_or(cfg.span.0);
+            let kind = if edge.kind == EdgeKind::Goto { "fallthrough".to_string() } else { edge.kind.to_string() };
+            let target = if edge.to == EXIT { "exit".to_string() } else { format!("bb{}", edge.to) };
+            format!("{}:{} {} edge bb{} → {} never taken", file_name, line, kind, edge.from, target)
+        }
+    }
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { covered as f64 / total as f64 * 100.0 }
+}
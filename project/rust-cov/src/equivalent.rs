@@ -0,0 +1,242 @@
+use std::collections::{BTreeMap, BTreeSet};
+use quote::ToTokens;
+use syn::visit::{self, Visit};
+use syn::visit_mut::{self, VisitMut};
+use syn::{BinOp, Expr, Lit, Stmt, UnOp};
+
+use crate::cfg::{self, contains, span_of};
+use crate::dataflow;
+use crate::mutation::{self, Mutant};
+use crate::Span;
+
+// Mutants statically known to behave like the original (or like an earlier
+// mutant), by id, with the reason.
+pub fn detect(source: &str, file: &syn::File, mutants: &[Mutant]) -> BTreeMap<usize, String> {
+    let reachable: Vec<Span> = cfg::build_all(file).iter().flat_map(|c| c.blocks.iter().flat_map(|b| b.spans.clone())).collect();
+    let mut dead_code = DeadCode { reachable, spans: Vec::new() };
+    dead_code.visit_file(file);
+    let mut dead_stores = DeadStores { sites: dead_store_sites(file), spans: Vec::new() };
+    dead_stores.visit_file(file);
+
+    let original = normalized(file.clone());
+    let mut seen: BTreeMap<String, usize> = BTreeMap::new();
+    let mut flagged = BTreeMap::new();
+    for mutant in mutants {
+        let reason = trivial(mutant)
+            .or_else(|| dead_code.spans.iter().any(|s| contains(s, &mutant.span)).then(|| "unreachable code".to_string()))
+            .or_else(|| dead_stores.spans.iter().find(|(s, _)| contains(s, &mutant.span)).map(|(_, var)| format!("dead store to `{}`", var)))
+            .or_else(|| {
+                let mutated = normalized(syn::parse_file(&mutation::apply(source, mutant)).ok()?);
+                if mutated == original {
+                    return Some("same normalized AST as the original".to_string());
+                }
+                match seen.get(&mutated) {
+                    Some(id) => Some(format!("duplicate of mutant #{}", id)),
+                    None => {
+                        seen.insert(mutated, mutant.id);
+                        None
+                    }
+                }
+            });
+        if let Some(reason) = reason {
+            flagged.insert(mutant.id, reason);
+        }
+    }
+    flagged
+}
+
+// Operator changes that cannot alter the value: `b && b` / `b || b`,
+// `x * 1` / `x / 1`, `x + 0` / `x - 0`, `x <= x` / `x >= x` / `true`, `-0` / `0`.
+fn trivial(mutant: &Mutant) -> Option<String> {
+    let before: Expr = syn::parse_str(&mutant.before).ok()?;
+    let after: Expr = syn::parse_str(&mutant.after).ok()?;
+    let same = match (&before, &after) {
+        (Expr::Binary(b), Expr::Binary(a)) => {
+            let operands_equal = tokens(&b.left) == tokens(&b.right);
+            let ops = [op_group(&b.op, &b.right, operands_equal), op_group(&a.op, &a.right, operands_equal)];
+            ops[0].is_some() && ops[0] == ops[1]
+        }
+        // a condition comparing an expression with itself is constant
+        (Expr::Binary(b), Expr::Lit(syn::ExprLit { lit: Lit::Bool(value), .. })) => {
+            let constant = if tokens(&b.left) == tokens(&b.right) { op_group(&b.op, &b.right, true) } else { None };
+            constant == Some(if value.value { "reflexive" } else { "irreflexive" })
+        }
+        (Expr::Unary(u), _) => matches!(u.op, UnOp::Neg(_)) && is_zero(&u.expr),
+        _ => false,
+    };
+    same.then(|| format!("`{}` equals `{}`", mutant.before, mutant.after))
+}
+
+// Operators yielding the same result for this right operand; `operands_equal`
+// says whether both sides are the same expression.
+fn op_group(op: &BinOp, right: &Expr, operands_equal: bool) -> Option<&'static str> {
+    let group = match op {
+        BinOp::And(_) | BinOp::Or(_) if operands_equal => "idempotent",
+        BinOp::Le(_) | BinOp::Ge(_) | BinOp::Eq(_) if operands_equal => "reflexive",
+        BinOp::Lt(_) | BinOp::Gt(_) | BinOp::Ne(_) if operands_equal => "irreflexive",
+        BinOp::Mul(_) | BinOp::Div(_) if is_one(right) => "times one",
+        BinOp::MulAssign(_) | BinOp::DivAssign(_) if is_one(right) => "times one assign",
+        BinOp::Add(_) | BinOp::Sub(_) if is_zero(right) => "plus zero",
+        BinOp::AddAssign(_) | BinOp::SubAssign(_) if is_zero(right) => "plus zero assign",
+        _ => return None,
+    };
+    Some(group)
+}
+
+fn int_value(expr: &Expr) -> Option<String> {
+    match expr {
+        Expr::Lit(syn::ExprLit { lit: Lit::Int(n), .. }) => Some(n.base10_digits().to_string()),
+        Expr::Lit(syn::ExprLit { lit: Lit::Float(f), .. }) => f.base10_digits().parse::<f64>().ok().map(|f| f.to_string()),
+        Expr::Paren(p) => int_value(&p.expr),
+        _ => None,
+    }
+}
+
+fn is_zero(expr: &Expr) -> bool {
+    int_value(expr).as_deref() == Some("0")
+}
+
+fn is_one(expr: &Expr) -> bool {
+    int_value(expr).as_deref() == Some("1")
+}
+
+fn tokens<T: ToTokens>(node: &T) -> String {
+    node.to_token_stream().to_string()
+}
+
+// Statements of function bodies no CFG block holds any code of (e.g. after a
+// `return` or a panic).
+struct DeadCode {
+    reachable: Vec<Span>,
+    spans: Vec<Span>,
+}
+
+impl<'ast> Visit<'ast> for DeadCode {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        // like the CFG, loops are judged by the statements of their body
+        let owns_code = !matches!(s, Stmt::Expr(Expr::While(_) | Expr::ForLoop(_) | Expr::Loop(_), _) | Stmt::Item(_));
+        if let Some(span) = crate::stmt_span(s).filter(|_| owns_code) {
+            if !self.reachable.iter().any(|r| contains(r, &span) || contains(&span, r)) {
+                self.spans.push(span_of(s));
+                return;
+            }
+        }
+        visit::visit_stmt(self, s);
+    }
+}
+
+// Definitions no use is reached by, keyed by their site, with the variable.
+// Variables captured by format strings (`"{x}"`) are invisible to the def-use
+// analysis, so their definitions are never taken for dead.
+fn dead_store_sites(file: &syn::File) -> BTreeMap<Span, String> {
+    let mut macros = MacroTokens(String::new());
+    macros.visit_file(file);
+    let mut sites = BTreeMap::new();
+    for du in dataflow::analyze_all(file) {
+        let used: BTreeSet<usize> = du.pairs.iter().map(|(def, _)| *def).collect();
+        let live_sites: BTreeSet<Span> = du.defs.iter().enumerate().filter(|(id, _)| used.contains(id)).map(|(_, d)| d.site).collect();
+        let captured = |var: &str| macros.0.contains(&format!("{{{}}}", var)) || macros.0.contains(&format!("{{{}:", var));
+        for def in du.defs.iter().filter(|d| d.site.0 != du.sig_line && !live_sites.contains(&d.site) && !captured(&d.var)) {
+            sites.insert(def.site, def.var.clone());
+        }
+    }
+    sites
+}
+
+struct MacroTokens(String);
+
+impl<'ast> Visit<'ast> for MacroTokens {
+    fn visit_macro(&mut self, mac: &'ast syn::Macro) {
+        self.0 += &mac.tokens.to_string();
+    }
+}
+
+// The stored values of dead stores, when computing them has no side effects.
+struct DeadStores {
+    sites: BTreeMap<Span, String>,
+    spans: Vec<(Span, String)>,
+}
+
+impl<'ast> Visit<'ast> for DeadStores {
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        // assignments are defined at the expression, declarations at the statement
+        let site = match s {
+            Stmt::Expr(e, _) => Some(span_of(e)),
+            _ => crate::stmt_span(s),
+        };
+        if let Some(var) = site.and_then(|span| self.sites.get(&span)) {
+            let stored = match s {
+                Stmt::Local(local) => local.init.as_ref().filter(|init| init.diverge.is_none()).map(|init| &*init.expr),
+                // stores through fields, indices or references outlive the function
+                Stmt::Expr(Expr::Assign(a), _) if is_local(&a.left) => Some(&*a.right),
+                Stmt::Expr(Expr::Binary(b), _) if is_compound_assign(&b.op) && is_local(&b.left) => Some(&*b.right),
+                _ => None,
+            };
+            if stored.is_some_and(|e| !has_side_effects(e)) {
+                self.spans.push((span_of(s), var.clone()));
+                return;
+            }
+        }
+        visit::visit_stmt(self, s);
+    }
+}
+
+fn is_local(expr: &Expr) -> bool {
+    matches!(expr, Expr::Path(p) if p.path.get_ident().is_some())
+}
+
+fn is_compound_assign(op: &BinOp) -> bool {
+    matches!(op, BinOp::AddAssign(_) | BinOp::SubAssign(_) | BinOp::MulAssign(_) | BinOp::DivAssign(_)
+        | BinOp::RemAssign(_) | BinOp::BitAndAssign(_) | BinOp::BitOrAssign(_) | BinOp::BitXorAssign(_)
+        | BinOp::ShlAssign(_) | BinOp::ShrAssign(_))
+}
+
+fn has_side_effects(expr: &Expr) -> bool {
+    struct Effects(bool);
+    impl<'ast> Visit<'ast> for Effects {
+        fn visit_expr(&mut self, e: &'ast Expr) {
+            match e {
+                Expr::Call(_) | Expr::MethodCall(_) | Expr::Macro(_) | Expr::Assign(_) | Expr::Await(_) => self.0 = true,
+                Expr::Binary(b) if is_compound_assign(&b.op) => self.0 = true,
+                _ => visit::visit_expr(self, e),
+            }
+        }
+    }
+    let mut effects = Effects(false);
+    effects.visit_expr(expr);
+    effects.0
+}
+
+// A canonical rendering of the file: parentheses dropped, `a > b` written as
+// `b < a`, and operands of commutative operators in a fixed order.
+fn normalized(mut file: syn::File) -> String {
+    Normalizer.visit_file_mut(&mut file);
+    tokens(&file)
+}
+
+struct Normalizer;
+
+impl VisitMut for Normalizer {
+    fn visit_expr_mut(&mut self, e: &mut Expr) {
+        while let Expr::Paren(p) = e {
+            *e = (*p.expr).clone();
+        }
+        visit_mut::visit_expr_mut(self, e);
+        if let Expr::Binary(b) = e {
+            let flipped = match b.op {
+                BinOp::Gt(_) => Some(BinOp::Lt(Default::default())),
+                BinOp::Ge(_) => Some(BinOp::Le(Default::default())),
+                _ => None,
+            };
+            if let Some(op) = flipped {
+                b.op = op;
+                std::mem::swap(&mut b.left, &mut b.right);
+            }
+            let commutative = matches!(b.op, BinOp::Add(_) | BinOp::Mul(_) | BinOp::Eq(_) | BinOp::Ne(_)
+                | BinOp::BitAnd(_) | BinOp::BitOr(_) | BinOp::BitXor(_));
+            if commutative && tokens(&b.left) > tokens(&b.right) {
+                std::mem::swap(&mut b.left, &mut b.right);
+            }
+        }
+    }
+}
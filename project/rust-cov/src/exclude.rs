@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+use syn::Attribute;
+
+const IGNORE_NEXT_LINE: &str = "rust-cov:ignore-next-line";
+const IGNORE_START: &str = "rust-cov:ignore-start";
+const IGNORE_END: &str = "rust-cov:ignore-end";
+
+// syn drops comments, so the markers are collected by lexing the raw source.
+pub struct Exclusions {
+    lines: BTreeMap<usize, String>,
+}
+
+impl Exclusions {
+    pub fn from_source(source: &str) -> Self {
+        let mut lines = BTreeMap::new();
+        let mut region_start = None;
+
+        let comments = line_comments(source);
+        for line in 1..=source.lines().count() {
+            let comment = comments.get(&line).map_or("", |text| text.trim());
+
+            if comment.starts_with(IGNORE_END) {
+                region_start = None;
+                continue;
+            }
+            if let Some(start) = region_start {
+                lines.insert(line, format!("{} at line {}", IGNORE_START, start));
+            }
+            if comment.starts_with(IGNORE_START) {
+                region_start = Some(line);
+            } else if comment.starts_with(IGNORE_NEXT_LINE) {
+                lines.insert(line + 1, format!("{} at line {}", IGNORE_NEXT_LINE, line));
+            }
+        }
+
+        Self { lines }
+    }
+
+    pub fn reason(&self, line: usize) -> Option<&String> {
+        self.lines.get(&line)
+    }
+}
+
+// The text after `//` of the comment ending each line, skipping `//` inside
+// string, raw string and char literals and inside block comments.
+fn line_comments(source: &str) -> BTreeMap<usize, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut comments = BTreeMap::new();
+    let (mut i, mut line) = (0, 1);
+    // skips to past `end`, counting the lines on the way
+    let skip_to = |i: &mut usize, line: &mut usize, end: &dyn Fn(usize) -> Option<usize>| {
+        while *i < chars.len() {
+            if let Some(len) = end(*i) {
+                *i += len;
+                return;
+            }
+            if chars[*i] == '\n' {
+                *line += 1;
+            }
+            *i += 1;
+        }
+    };
+    while i < chars.len() {
+        let at = |offset: usize| chars.get(i + offset).copied();
+        let after_ident = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        match chars[i] {
+            '\n' => {
+                line += 1;
+                i += 1;
+            }
+            '/' if at(1) == Some('/') => {
+                let end = chars[i..].iter().position(|c| *c == '\n').map_or(chars.len(), |len| i + len);
+                comments.insert(line, chars[i + 2..end].iter().collect());
+                i = end;
+            }
+            '/' if at(1) == Some('*') => {
+                // block comments nest
+                let mut depth = 0;
+                while i < chars.len() {
+                    match (chars[i], chars.get(i + 1)) {
+                        ('/', Some('*')) => (depth, i) = (depth + 1, i + 2),
+                        ('*', Some('/')) => (depth, i) = (depth - 1, i + 2),
+                        (c, _) => {
+                            line += (c == '\n') as usize;
+                            i += 1;
+                        }
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                i += 1;
+                skip_to(&mut i, &mut line, &|j| match chars[j] {
+                    '"' if chars[..j].iter().rev().take_while(|c| **c == '\\').count() % 2 == 0 => Some(1),
+                    _ => None,
+                });
+            }
+            'r' | 'b' if !after_ident && raw_string_hashes(&chars[i..]).is_some() => {
+                let (prefix, hashes) = raw_string_hashes(&chars[i..]).unwrap_or_default();
+                i += prefix;
+                skip_to(&mut i, &mut line, &|j| {
+                    let closes = chars[j] == '"' && (1..=hashes).all(|k| chars.get(j + k) == Some(&'#'));
+                    closes.then_some(hashes + 1)
+                });
+            }
+            // a char literal, unlike a lifetime, is closed right away
+            '\'' if at(1) == Some('\\') => {
+                i += 2;
+                skip_to(&mut i, &mut line, &|j| (chars[j] == '\'').then_some(1));
+            }
+            '\'' if at(2) == Some('\'') => i += 3,
+            _ => i += 1,
+        }
+    }
+    comments
+}
+
+// The length up to the opening quote and the number of `#` of a raw string
+// starting `r"`, `r#"`, `br"` ...
+fn raw_string_hashes(chars: &[char]) -> Option<(usize, usize)> {
+    let start = if chars.first() == Some(&'b') { 1 } else { 0 };
+    if chars.get(start) != Some(&'r') {
+        return None;
+    }
+    let hashes = chars[start + 1..].iter().take_while(|c| **c == '#').count();
+    (chars.get(start + 1 + hashes) == Some(&'"')).then_some((start + 2 + hashes, hashes))
+}
+
+// Some(false) for `#[coverage(off)]`, Some(true) for `#[coverage(on)]`
+pub fn coverage_attr(attrs: &[Attribute]) -> Option<bool> {
+    attrs.iter()
+        .rev()
+        .filter(|attr| attr.path().is_ident("coverage"))
+        .filter_map(|attr| attr.parse_args::<syn::Ident>().ok())
+        .find_map(|arg| match arg.to_string().as_str() {
+            "off" => Some(false),
+            "on" => Some(true),
+            _ => None,
+        })
+}
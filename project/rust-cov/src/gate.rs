@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use std::fs;
+
+use crate::cobertura::module_path;
+use crate::summary::FileCoverage;
+
+// Minimum percentages, a kind left out is not checked.
+#[derive(Deserialize, Default, Clone, Copy)]
+pub struct Thresholds {
+    pub functions: Option<f64>,
+    pub statements: Option<f64>,
+    pub branches: Option<f64>,
+    pub mcdc: Option<f64>,
+}
+
+// Thresholds for every file matching `glob`, given as a file path
+// (`src/parser/*.rs`) or, with `::`, a module path (`crate::parser::**`).
+#[derive(Deserialize)]
+pub struct PathThresholds {
+    pub glob: String,
+    #[serde(flatten)]
+    pub thresholds: Thresholds,
+}
+
+// The `[thresholds]` table of `rust-cov.toml`:
+//
+//     [thresholds]
+//     functions = 90
+//     branches = 75
+//
+//     [[thresholds.paths]]
+//     glob = "crate::legacy::**"
+//     branches = 40
+#[derive(Deserialize, Default)]
+pub struct Config {
+    #[serde(default)]
+    pub thresholds: Rules,
+}
+
+#[derive(Deserialize, Default)]
+pub struct Rules {
+    #[serde(flatten)]
+    pub global: Thresholds,
+    #[serde(default)]
+    pub paths: Vec<PathThresholds>,
+}
+
+impl Config {
+    pub fn load(path: &str) -> Self {
+        let contents = fs::read_to_string(path).expect("Something went wrong reading the config");
+        let table: toml::Table = toml::from_str(&contents).unwrap_or_else(|e| panic!("Something went wrong parsing {}: {}", path, e));
+        // serde cannot reject unknown keys next to flattened ones, and a
+        // misspelled threshold would silently pass the gate
+        if let Some(toml::Value::Table(thresholds)) = table.get("thresholds") {
+            let rules = thresholds.get("paths").and_then(toml::Value::as_array).into_iter().flatten().filter_map(toml::Value::as_table);
+            for (table, allowed) in std::iter::once((thresholds, "paths")).chain(rules.map(|rule| (rule, "glob"))) {
+                if let Some(key) = table.keys().find(|key| !KINDS.contains(&key.as_str()) && key.as_str() != allowed) {
+                    panic!("Unknown threshold `{}` in {}, expected one of {}", key, path, KINDS.join(", "));
+                }
+            }
+        }
+        table.try_into().unwrap_or_else(|e| panic!("Something went wrong parsing {}: {}", path, e))
+    }
+}
+
+const KINDS: [&str; 4] = ["functions", "statements", "branches", "mcdc"];
+
+// Checks the totals of all files against the global thresholds and every
+// file against the thresholds of the globs it matches. `mcdc` is the
+// (covered, total) conditions of the traced file, the only one given with
+// traces; MC/DC is not checked without it. Returns the violations.
+pub fn check(files: &[FileCoverage], mcdc: Option<(usize, usize)>, config: &Rules) -> Vec<String> {
+    let mut violations = Vec::new();
+    let sum = |f: &dyn Fn(&FileCoverage) -> (usize, usize)| files.iter().map(f).fold((0, 0), |(c, t), (fc, ft)| (c + fc, t + ft));
+    let total = [
+        sum(&|file| (file.functions_covered(), file.functions.len())),
+        sum(&|file| (file.statements_covered(), file.statements.len())),
+        sum(&|file| (file.arms_covered(), file.arms())),
+    ];
+    violations.extend(compare("total", &config.global, total, mcdc));
+
+    for file in files {
+        let module = module_path(&file.path);
+        let ratios = [
+            (file.functions_covered(), file.functions.len()),
+            (file.statements_covered(), file.statements.len()),
+            (file.arms_covered(), file.arms()),
+        ];
+        for rule in &config.paths {
+            let matched = if rule.glob.contains("::") { matches(&rule.glob, &module) } else { matches_path(&rule.glob, &file.path) };
+            if matched {
+                violations.extend(compare(&format!("{} ({})", file.path, rule.glob), &rule.thresholds, ratios, mcdc));
+            }
+        }
+    }
+    violations
+}
+
+// "<scope>: branches 62.50% (5/8) is below 75.00%" for every threshold missed.
+fn compare(scope: &str, thresholds: &Thresholds, [functions, statements, branches]: [(usize, usize); 3],
+           mcdc: Option<(usize, usize)>) -> Vec<String> {
+    let checks = [
+        ("functions", thresholds.functions, Some(functions)),
+        ("statements", thresholds.statements, Some(statements)),
+        ("branches", thresholds.branches, Some(branches)),
+        ("MC/DC", thresholds.mcdc, mcdc),
+    ];
+    checks.iter()
+        .filter_map(|(kind, minimum, ratio)| {
+            let (minimum, (covered, total)) = (minimum.as_ref()?, ratio.as_ref()?);
+            let percent = if *total == 0 { 100.0 } else { *covered as f64 / *total as f64 * 100.0 };
+            (percent < *minimum).then(|| format!("{}: {} {:.2}% ({}/{}) is below {:.2}%", scope, kind, percent, covered, total, minimum))
+        })
+        .collect()
+}
+
+// A relative glob may match the end of the path, so `src/*.rs` matches
+// `../app/src/main.rs`.
+fn matches_path(glob: &str, path: &str) -> bool {
+    let path = path.trim_start_matches("./");
+    if glob.starts_with('/') {
+        return matches(glob, path);
+    }
+    matches(glob, path) || path.match_indices('/').any(|(idx, _)| matches(glob, &path[idx + 1..]))
+}
+
+// `**/` matches any number of directories, `**` anything, `*` anything but a
+// path or module separator, `?` one character.
+fn matches(glob: &str, text: &str) -> bool {
+    let (glob, text): (Vec<char>, Vec<char>) = (glob.chars().collect(), text.chars().collect());
+    matches_at(&glob, &text)
+}
+
+fn matches_at(glob: &[char], text: &[char]) -> bool {
+    match glob {
+        [] => text.is_empty(),
+        ['*', '*', '/', rest @ ..] if matches_at(rest, text) => true,
+        ['*', '*', rest @ ..] => (0..=text.len()).any(|skip| matches_at(rest, &text[skip..])),
+        ['*', rest @ ..] => {
+            let run = text.iter().take_while(|c| !matches!(c, '/' | ':')).count();
+            (0..=run).any(|skip| matches_at(rest, &text[skip..]))
+        }
+        ['?', rest @ ..] => !text.is_empty() && matches_at(rest, &text[1..]),
+        [c, rest @ ..] => text.first() == Some(c) && matches_at(rest, &text[1..]),
+    }
+}
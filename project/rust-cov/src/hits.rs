@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::Span;
+
+// Line hits as produced for result/llvm_result.txt: one covered line per row,
+// optionally followed by its execution count (`<line> <count>`).
+pub struct LineHits {
+    counts: BTreeMap<usize, u64>,
+    // whether the rows carried execution counts or only covered lines
+    has_counts: bool,
+}
+
+impl LineHits {
+    pub fn load(path: &str) -> Self {
+        let contents = fs::read_to_string(path).expect("Something went wrong reading the hits file");
+        Self::parse(&contents)
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        let mut counts = BTreeMap::new();
+        let mut has_counts = false;
+        for row in contents.lines() {
+            let mut fields = row.split_whitespace();
+            let Some(line) = fields.next().and_then(|f| f.parse::<usize>().ok()) else {
+                continue;
+            };
+            let count = match fields.next().and_then(|f| f.parse::<u64>().ok()) {
+                Some(count) => {
+                    has_counts = true;
+                    count
+                }
+                None => 1,
+            };
+            *counts.entry(line).or_insert(0) += count;
+        }
+        Self { counts, has_counts }
+    }
+
+    // Counts of the lines of an execution trace, one hit per occurrence.
+    pub fn from_trace(trace: &[usize]) -> Self {
+        let mut counts = BTreeMap::new();
+        for &line in trace {
+            *counts.entry(line).or_insert(0) += 1;
+        }
+        Self { counts, has_counts: true }
+    }
+
+    // Lines missing from the file were not executed.
+    pub fn count(&self, line: usize) -> u64 {
+        self.counts.get(&line).copied().unwrap_or(0)
+    }
+
+    pub fn has_counts(&self) -> bool {
+        self.has_counts
+    }
+
+    // Execution count of the code owning `span`, taken from its first line.
+    pub fn span_count(&self, span: &Span) -> u64 {
+        self.count(span.0)
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::Path;
+
+use crate::summary::{Branch, FileCoverage};
+
+// Inline so the pages need no other file or network access.
+const STYLE: &str = "html, body { margin: 0; padding: 0; font-family: sans-serif; }
+.app { margin: 10px; }
+.files-list { margin: 10px 0 0; width: 100%; border-collapse: collapse; }
+.files-list th { padding: 10px; border: 1px solid #999; text-align: left; font-weight: normal; background: #ddd; }
+.files-list td { padding: 10px; border: 1px solid #999; }
+.files-list a { color: #338; }
+.files-list__file_low { background: #fcc; }
+.files-list__file_medium { background: #ffc; }
+.files-list__file_high { background: #cfc; }
+.file-header { border: 1px solid #999; display: flex; justify-content: space-between; align-items: center;
+  position: sticky; top: 0; background: white; }
+.file-header > * { margin: 10px; }
+.file-header__name { flex-grow: 2; }
+.source { margin: 10px 0 0; border: 1px solid #999; border-collapse: collapse; width: 100%;
+  font-family: monospace; white-space: pre; }
+.source td { padding: 0 8px; vertical-align: top; }
+.source .number, .source .hits { text-align: right; color: #666; border-right: 1px solid #ccc; width: 1%; }
+.source .branch { width: 1%; }
+.line_covered { background: #cfc; }
+.line_uncovered { background: #fcc; }
+.line_partial { background: #ffc; }
+.marker { cursor: help; border-bottom: 1px dotted #333; }
+";
+
+// Writes `index.html` with a summary of every file into `dir`, next to one
+// page per file with its source annotated with hit counts, uncovered lines
+// and the arms taken at every decision. Returns the pages written.
+pub fn write(dir: &str, files: &[FileCoverage]) -> Vec<String> {
+    fs::create_dir_all(dir).expect("Something went wrong creating the report directory");
+    let mut written = Vec::new();
+
+    let mut rows = String::new();
+    for file in files {
+        let lines = (file.lines_covered(), file.lines.len());
+        let functions = (file.functions.iter().filter(|f| f.hits > 0).count(), file.functions.len());
+        let branches = (file.arms_covered(), file.arms());
+        rows += &format!("<tr class=\"{}\"><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                         level(lines), page_name(&file.path), escape(&file.path), ratio(lines), ratio(functions), ratio(branches));
+
+        let page = Path::new(dir).join(page_name(&file.path));
+        fs::write(&page, file_page(file)).expect("Something went wrong writing the report");
+        written.push(page.to_string_lossy().into_owned());
+    }
+
+    let total = |f: &dyn Fn(&FileCoverage) -> (usize, usize)| files.iter().map(f).fold((0, 0), |(c, t), (fc, ft)| (c + fc, t + ft));
+    let lines = total(&|file| (file.lines_covered(), file.lines.len()));
+    let functions = total(&|file| (file.functions.iter().filter(|f| f.hits > 0).count(), file.functions.len()));
+    let branches = total(&|file| (file.arms_covered(), file.arms()));
+    let body = format!("<div class=\"file-header\"><span class=\"file-header__name\">rust-cov coverage report</span>\
+                        <span>lines {}</span><span>functions {}</span><span>branches {}</span></div>\n\
+                        <table class=\"files-list\">\n<thead><tr><th>File</th><th>Lines</th><th>Functions</th><th>Branches</th></tr></thead>\n\
+                        <tbody>\n{}</tbody>\n</table>\n", ratio(lines), ratio(functions), ratio(branches), rows);
+    let index = Path::new(dir).join("index.html");
+    fs::write(&index, page("Coverage report", &body)).expect("Something went wrong writing the report");
+    written.insert(0, index.to_string_lossy().into_owned());
+    written
+}
+
+fn file_page(file: &FileCoverage) -> String {
+    let lines = (file.lines_covered(), file.lines.len());
+    let mut body = format!("<div class=\"file-header\"><a href=\"index.html\">← back</a>\
+                            <span class=\"file-header__name\">{}</span><span>lines {}</span><span>branches {}</span></div>\n",
+                           escape(&file.path), ratio(lines), ratio((file.arms_covered(), file.arms())));
+    body += "<table class=\"source\">\n";
+    for (idx, text) in file.source.lines().enumerate() {
+        let number = idx + 1;
+        let branches: Vec<&Branch> = file.branches.iter().filter(|branch| branch.line == number).collect();
+        let count = file.lines.get(&number);
+        let partial = branches.iter().any(|branch| branch.covered() < branch.arms.len());
+        let (class, hits) = match count {
+            Some(0) => ("line_uncovered", "0".to_string()),
+            Some(count) if partial => ("line_partial", count.to_string()),
+            Some(count) => ("line_covered", count.to_string()),
+            None => ("", String::new()),
+        };
+        let function = file.functions.iter().find(|f| f.hits == 0 && number == f.span.0);
+        let hits = match (function, count) {
+            (Some(f), _) => format!("<span class=\"marker\" title=\"{} is never called\">{}</span>", escape(&f.name), hits),
+            (None, Some(0)) => "<span class=\"marker\" title=\"never executed\">0</span>".to_string(),
+            _ => hits,
+        };
+        let marker = if branches.is_empty() { String::new() } else {
+            let taken: usize = branches.iter().map(|branch| branch.covered()).sum();
+            let arms: usize = branches.iter().map(|branch| branch.arms.len()).sum();
+            let details: Vec<String> = branches.iter().map(|branch| describe(branch)).collect();
+            format!("<span class=\"marker\" title=\"{}\">{}/{}</span>", details.join("&#10;"), taken, arms)
+        };
+        body += &format!("<tr class=\"{}\"><td class=\"number\">{}</td><td class=\"hits\">{}</td><td class=\"branch\">{}</td><td>{}</td></tr>\n",
+                         class, number, hits, marker, escape(text));
+    }
+    body += "</table>\n";
+    page(&file.path, &body)
+}
+
+// `cond`: true taken 2×, false never taken
+fn describe(branch: &Branch) -> String {
+    let arms: Vec<String> = branch.arm_names.iter().zip(&branch.arms)
+        .map(|(name, taken)| match taken {
+            None => format!("{} not evaluated", escape(name)),
+            Some(0) => format!("{} never taken", escape(name)),
+            Some(count) => format!("{} taken {}×", escape(name), count),
+        })
+        .collect();
+    format!("`{}`: {}", escape(&branch.text), arms.join(", "))
+}
+
+fn page(title: &str, body: &str) -> String {
+    format!("<!doctype html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>{}</style>\n</head>\n\
+             <body>\n<div class=\"app\">\n{}</div>\n</body>\n</html>\n", escape(title), STYLE, body)
+}
+
+fn page_name(path: &str) -> String {
+    let name: String = path.chars().map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' }).collect();
+    format!("{}.html", name.trim_start_matches('.'))
+}
+
+fn ratio((covered, total): (usize, usize)) -> String {
+    let percent = if total == 0 { 100.0 } else { covered as f64 / total as f64 * 100.0 };
+    format!("{}/{} ({:.2}%)", covered, total, percent)
+}
+
+// Shading as in tarpaulin's report.
+fn level((covered, total): (usize, usize)) -> &'static str {
+    let percent = if total == 0 { 100.0 } else { covered as f64 / total as f64 * 100.0 };
+    if percent < 50.0 {
+        "files-list__file_low"
+    } else if percent < 80.0 {
+        "files-list__file_medium"
+    } else {
+        "files-list__file_high"
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
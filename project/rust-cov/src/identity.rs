@@ -0,0 +1,43 @@
+use std::collections::BTreeMap;
+
+use crate::hits::LineHits;
+use crate::Coverage;
+
+// Comments and spacing do not change the tokens.
+pub fn normalize(code: &str) -> String {
+    match code.parse::<proc_macro2::TokenStream>() {
+        Ok(tokens) => tokens.to_string(),
+        Err(_) => code.split_whitespace().collect::<Vec<_>>().join(" "),
+    }
+}
+
+// FNV-1a, spelled out so ids stay the same across Rust releases, unlike
+// `DefaultHasher`.
+pub fn fingerprint(structure: &str) -> u64 {
+    structure.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+// The execution counts of `old_hits`, recorded against `old`, moved to the
+// lines the same items start on in `new`. Returns the counts by new line and
+// the ids of the old items `new` no longer has.
+pub fn remap(old: &Coverage, old_hits: &LineHits, new: &Coverage) -> (BTreeMap<usize, u64>, Vec<String>) {
+    let new_lines: BTreeMap<&String, usize> = new.ids.iter()
+        .filter_map(|((kind, idx), id)| new.start_line(kind, *idx).map(|line| (id, line)))
+        .collect();
+
+    let mut counts = BTreeMap::new();
+    let mut lost = Vec::new();
+    for ((kind, idx), id) in &old.ids {
+        let Some(old_line) = old.start_line(kind, *idx) else {
+            continue;
+        };
+        match new_lines.get(id) {
+            Some(line) => {
+                let count = counts.entry(*line).or_insert(0);
+                *count = old_hits.count(old_line).max(*count);
+            }
+            None => lost.push(id.clone()),
+        }
+    }
+    (counts, lost)
+}
@@ -0,0 +1,31 @@
+use crate::summary::FileCoverage;
+
+// An LCOV tracefile record for one source file: `FN`/`FNDA` for the functions,
+// `DA` for the lines starting a function, statement or decision, and `BRDA`
+// for every arm of every decision and every condition (one block each).
+pub fn tracefile(file: &FileCoverage) -> String {
+    let mut text = format!("TN:\nSF:{}\n", file.path);
+
+    for function in &file.functions {
+        text += &format!("FN:{},{}\n", function.span.0, function.name);
+    }
+    for function in &file.functions {
+        text += &format!("FNDA:{},{}\n", function.hits, function.name);
+    }
+    text += &format!("FNF:{}\nFNH:{}\n", file.functions.len(), file.functions.iter().filter(|f| f.hits > 0).count());
+
+    for (block, branch) in file.branches.iter().enumerate() {
+        for (idx, taken) in branch.arms.iter().enumerate() {
+            let taken = taken.map_or("-".to_string(), |count| count.to_string());
+            text += &format!("BRDA:{},{},{},{}\n", branch.line, block, idx, taken);
+        }
+    }
+    text += &format!("BRF:{}\nBRH:{}\n", file.arms(), file.arms_covered());
+
+    for (line, count) in &file.lines {
+        text += &format!("DA:{},{}\n", line, count);
+    }
+    text += &format!("LF:{}\nLH:{}\n", file.lines.len(), file.lines_covered());
+    text += "end_of_record\n";
+    text
+}
@@ -1,16 +1,47 @@
-use std::collections::{BTreeMap, HashMap, HashSet};
-use quote::ToTokens;
-use serde::de::value::CowStrDeserializer;
-use syn::{spanned::Spanned, visit::{self, Visit}, Block, Expr, ExprIf, ExprLet, File, ItemFn, Local, Stmt};
+use std::collections::{BTreeMap, BTreeSet};
+use syn::{spanned::Spanned, visit::{self, Visit}, Expr, ExprIf, ItemFn, Stmt};
 use std::fs;
 
+mod annotate;
+mod attribution;
+mod baseline;
+mod cfg;
+mod changes;
+mod cobertura;
+mod complexity;
+mod dataflow;
+mod edge;
+mod equivalent;
+mod exclude;
+mod gate;
+mod hits;
+mod html;
+mod identity;
+mod lcov;
+mod markdown;
+mod mcdc;
+mod minimize;
+mod mutation;
+mod patch;
+mod path;
+mod probe;
+mod runner;
+mod sarif;
+mod schemata;
+mod summary;
+
+use exclude::Exclusions;
+
+// (start line, start column, end line, end column)
+type Span = (usize, usize, usize, usize);
+
 struct Coverage {
-    func_cov: BTreeMap<usize, (String, (usize, usize, usize, usize))>,
-    stmt_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
-    branch_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
+    func_cov: BTreeMap<usize, (String, Span)>,
+    stmt_cov: BTreeMap<usize, Span>,
+    branch_cov: BTreeMap<usize, Span>,
 
-    loop_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
-    macro_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
+    loop_cov: BTreeMap<usize, Span>,
+    macro_cov: BTreeMap<usize, Span>,
 
     func_total: usize,
     stmt_total: usize,
@@ -19,11 +50,20 @@ struct Coverage {
     loop_total: usize,
     macro_total: usize,
 
-    switch_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
-    binary_conditional_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
-    binary_conditional_specific_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
+    switch_cov: BTreeMap<usize, Span>,
+    binary_conditional_cov: BTreeMap<usize, Span>,
+    binary_conditional_specific_cov: BTreeMap<usize, Span>,
     binary_conditional_total: usize,
-    if_stmt_cov: BTreeMap<usize, (usize, usize, usize, usize)>,
+    if_stmt_cov: BTreeMap<usize, Span>,
+
+    // (kind, span, reason) of every item dropped by an exclusion marker
+    excluded: Vec<(&'static str, Span, String)>,
+
+    // func id -> (cyclomatic, cognitive) complexity
+    complexity: BTreeMap<usize, (usize, usize)>,
+
+    // (kind, id) -> stable id, which survives edits elsewhere in the file
+    ids: BTreeMap<(&'static str, usize), String>,
 }
 
 impl Coverage {
@@ -47,22 +87,41 @@ impl Coverage {
             binary_conditional_specific_cov: BTreeMap::new(),
             binary_conditional_total: 0,
             if_stmt_cov: BTreeMap::new(),
+            excluded: Vec::new(),
+            complexity: BTreeMap::new(),
+            ids: BTreeMap::new(),
         }
     }
 
+    fn start_line(&self, kind: &str, idx: usize) -> Option<usize> {
+        let span = match kind {
+            "func" => self.func_cov.get(&idx).map(|(_, span)| span),
+            "stmt" => self.stmt_cov.get(&idx),
+            "branch" => self.branch_cov.get(&idx),
+            "loop" => self.loop_cov.get(&idx),
+            "macro" => self.macro_cov.get(&idx),
+            _ => None,
+        };
+        span.map(|span| span.0)
+    }
+
+    fn id(&self, kind: &'static str, idx: usize) -> &str {
+        self.ids.get(&(kind, idx)).map_or("", String::as_str)
+    }
+
     fn report(&self) {
         println!("AST:");
         println!("- func: {}", self.func_cov.len());
         for (idx, (name, (start_l, start, end_l, end))) in &self.func_cov {
-            println!("  - {}: {}: {}:{}-{}:{}", idx, name, start_l, start, end_l, end);
+            println!("  - {}: {}: {}:{}-{}:{} [{}]", idx, name, start_l, start, end_l, end, self.id("func", *idx));
         }
         println!("- stmt: {}", self.stmt_cov.len());
         for (idx, (start_l, start, end_l, end)) in &self.stmt_cov {
-            println!("  - {}: {}:{}-{}:{}", idx, start_l, start, end_l, end);
+            println!("  - {}: {}:{}-{}:{} [{}]", idx, start_l, start, end_l, end, self.id("stmt", *idx));
         }
         println!("- branch: {}", self.branch_cov.len());
         for (idx, (start_l, start, end_l, end)) in &self.branch_cov {
-            println!("  - {}: {}:{}-{}:{}", idx, start_l, start, end_l, end);
+            println!("  - {}: {}:{}-{}:{} [{}]", idx, start_l, start, end_l, end, self.id("branch", *idx));
         }
 
         println!("\nFor detail check:");
@@ -95,6 +154,10 @@ impl Coverage {
         for (idx, (start_l, start, end_l, end)) in &self.if_stmt_cov {
             println!("  - {}: {}:{}-{}:{}", idx, start_l, start, end_l, end);
         }
+        println!("- excluded: {}", self.excluded.len());
+        for (kind, (start_l, start, end_l, end), reason) in &self.excluded {
+            println!("  - {} {}:{}-{}:{} ({})", kind, start_l, start, end_l, end, reason);
+        }
     }
 }
 
@@ -107,33 +170,155 @@ struct CoverageVisitor {
     current_loop: usize,
     current_macro: usize,
     current_binary_conditional: usize,
+
+    exclusions: Exclusions,
+    // reason of the innermost enclosing `#[coverage(off)]`, if any
+    coverage_off: Option<String>,
+
+    // ids of the enclosing functions (None for excluded ones) and the
+    // nesting level used by cognitive complexity
+    fn_stack: Vec<Option<usize>>,
+    nesting: usize,
+    else_if: bool,
+
+    source: String,
+    // the enclosing modules, impl types and functions, and how often each
+    // stable id was handed out so far
+    scope: Vec<String>,
+    seen: BTreeMap<String, usize>,
+}
+
+impl CoverageVisitor {
+    // Records the item as excluded (and returns true) when it is under
+    // `#[coverage(off)]` or starts on a line covered by a comment marker.
+    fn is_excluded(&mut self, kind: &'static str, span: Span) -> bool {
+        let reason = match (&self.coverage_off, self.exclusions.reason(span.0)) {
+            (Some(reason), _) | (None, Some(reason)) => reason.clone(),
+            (None, None) => return false,
+        };
+        self.coverage.excluded.push((kind, span, reason));
+        true
+    }
+
+    // `<scope>::<kind>#<hash>`, the hash taken over the tokens of the node's
+    // own code, e.g. an `if` without its blocks. The n-th repeat of an id gets
+    // a `~n` suffix.
+    fn stable_id(&mut self, kind: &'static str, idx: usize, structure: &str) {
+        let mut id = format!("{}::{}#{:016x}", self.scope.join("::"), kind, identity::fingerprint(&identity::normalize(structure)));
+        let repeats = self.seen.entry(id.clone()).or_insert(0);
+        if *repeats > 0 {
+            id += &format!("~{}", repeats);
+        }
+        *repeats += 1;
+        self.coverage.ids.insert((kind, idx), id);
+    }
+
+    fn scoped<F>(&mut self, name: String, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.scope.push(name);
+        f(self);
+        self.scope.pop();
+    }
+
+    // Registers a function or method and visits it with its id on `fn_stack`,
+    // so the complexity of its body is added to it.
+    fn enter_fn<F>(&mut self, fn_name: String, sig: &syn::Signature, span: Span, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.scope.push(sig.ident.to_string());
+        if self.is_excluded("func", span) {
+            self.fn_stack.push(None);
+        } else {
+            self.current_func += 1;
+            self.coverage.func_total += 1;
+            self.coverage.func_cov.insert(self.current_func, (fn_name, span));
+            self.coverage.complexity.insert(self.current_func, (1, 0));
+            self.fn_stack.push(Some(self.current_func));
+            // the signature only, so editing the body keeps the id
+            self.stable_id("func", self.current_func, &quote::ToTokens::to_token_stream(sig).to_string());
+        }
+
+        let nesting = std::mem::take(&mut self.nesting);
+        f(self);
+        self.nesting = nesting;
+        self.fn_stack.pop();
+        self.scope.pop();
+    }
+
+    fn add_complexity(&mut self, cyclomatic: usize, cognitive: usize) {
+        if let Some(Some(func)) = self.fn_stack.last() {
+            let entry = self.coverage.complexity.entry(*func).or_insert((1, 0));
+            entry.0 += cyclomatic;
+            entry.1 += cognitive;
+        }
+    }
+
+    fn nested<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        self.nesting += 1;
+        f(self);
+        self.nesting -= 1;
+    }
+
+    fn with_coverage_attr<F>(&mut self, attrs: &[syn::Attribute], item: String, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        let saved = self.coverage_off.clone();
+        match exclude::coverage_attr(attrs) {
+            Some(false) => self.coverage_off = Some(format!("#[coverage(off)] on {}", item)),
+            Some(true) => self.coverage_off = None,
+            None => {}
+        }
+        f(self);
+        self.coverage_off = saved;
+    }
 }
 
 impl<'ast> Visit<'ast> for CoverageVisitor {
-    fn visit_item_fn(&mut self, item_fn: &'ast ItemFn) {
-        let fn_name = item_fn.sig.ident.to_string();
-        //let fn_source_code = item_fn.span().source_text().unwrap();
-        let span_start_line = item_fn.span().start().line;
-        let span_start = item_fn.span().start().column;
-        let span_end_line = item_fn.span().end().line;
-        let span_end = item_fn.span().end().column;
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        self.with_coverage_attr(&i.attrs, format!("mod {}", i.ident), |v| v.scoped(i.ident.to_string(), |v| visit::visit_item_mod(v, i)));
+    }
 
-        self.current_func += 1;
-        self.coverage.func_total += 1;
-        self.coverage.func_cov.insert(self.current_func, (fn_name, (span_start_line, span_start, span_end_line, span_end)));
+    fn visit_item_impl(&mut self, i: &'ast syn::ItemImpl) {
+        let self_ty = quote::ToTokens::to_token_stream(&i.self_ty).to_string();
+        let scope = self_ty.replace(' ', "");
+        self.with_coverage_attr(&i.attrs, format!("impl {}", self_ty), |v| v.scoped(scope, |v| visit::visit_item_impl(v, i)));
+    }
 
-        visit::visit_item_fn(self, item_fn);
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        self.with_coverage_attr(&i.attrs, format!("fn {}", i.sig.ident), |v| {
+            // methods are named after the impl's type, which is the innermost scope
+            let name = match v.scope.last() {
+                Some(self_ty) => format!("{}::{}", self_ty, i.sig.ident),
+                None => i.sig.ident.to_string(),
+            };
+            v.enter_fn(name, &i.sig, cfg::span_of(i), |v| visit::visit_impl_item_fn(v, i));
+        });
     }
 
-    fn visit_stmt(&mut self, s: &'ast Stmt) {
-        let span_start_line = s.span().start().line;
-        let span_start = s.span().start().column;
-        let span_end_line = s.span().end().line;
-        let span_end = s.span().end().column;
+    fn visit_item_fn(&mut self, item_fn: &'ast ItemFn) {
+        self.with_coverage_attr(&item_fn.attrs, format!("fn {}", item_fn.sig.ident), |v| {
+            v.enter_fn(item_fn.sig.ident.to_string(), &item_fn.sig, cfg::span_of(item_fn), |v| visit::visit_item_fn(v, item_fn));
+        });
+    }
 
-        self.current_stmt += 1;
-        self.coverage.stmt_total += 1;
-        self.coverage.stmt_cov.insert(self.current_stmt, (span_start_line, span_start, span_end_line, span_end));
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        if let Some(span) = stmt_span(s) {
+            if !self.is_excluded("stmt", span) {
+                self.current_stmt += 1;
+                self.coverage.stmt_total += 1;
+                self.coverage.stmt_cov.insert(self.current_stmt, span);
+                // compound statements end at their header
+                let structure = snippet(&self.source, &span);
+                self.stable_id("stmt", self.current_stmt, &structure);
+            }
+        }
 
         visit::visit_stmt(self, s);
     }
@@ -190,8 +375,15 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
+        if let syn::BinOp::And(_) | syn::BinOp::Or(_) = i.op {
+            // the operands are not visited, so the whole `&&` / `||` chain is counted here
+            let ops = logical_ops(&syn::Expr::Binary(i.clone()));
+            let sequences = 1 + ops.windows(2).filter(|w| w[0] != w[1]).count();
+            self.add_complexity(ops.len(), sequences);
+        }
+
         match i.op {
-            syn::BinOp::And(_) | syn::BinOp::Or(_) => {
+            syn::BinOp::And(_) | syn::BinOp::Or(_) if !self.is_excluded("binary conditional", (span_start_line, span_start, span_end_line, span_end)) => {
                 self.current_binary_conditional += 1;
                 self.coverage.binary_conditional_total += 1;
                 self.coverage.binary_conditional_cov.insert(self.current_binary_conditional, (span_start_line, span_start, span_end_line, span_end));
@@ -212,18 +404,37 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.cond.span().end().line;
         let span_end = i.cond.span().end().column;
 
-        self.current_branch += 1;
-        self.coverage.branch_total += 1;
-        self.coverage.branch_cov.insert(self.current_branch, (span_start_line, span_start, span_end_line, span_end));
-        
+        let excluded = self.is_excluded("branch", (span_start_line, span_start, span_end_line, span_end));
+        if !excluded {
+            self.current_branch += 1;
+            self.coverage.branch_total += 1;
+            self.coverage.branch_cov.insert(self.current_branch, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("branch", self.current_branch, &format!("if {}", quote::ToTokens::to_token_stream(&i.cond)));
+        }
+
+        // an `else if` adds no nesting penalty
+        if std::mem::take(&mut self.else_if) {
+            self.add_complexity(1, 1);
+        } else {
+            self.add_complexity(1, 1 + self.nesting);
+        }
+
         visit::visit_expr(self, &i.cond);
 
-        visit::visit_block(self, &i.then_branch);
+        self.nested(|v| visit::visit_block(v, &i.then_branch));
         if let Some((_, else_branch)) = &i.else_branch {
-            visit::visit_expr(self, else_branch);
+            if let Expr::If(_) = else_branch.as_ref() {
+                self.else_if = true;
+                visit::visit_expr(self, else_branch);
+            } else {
+                self.add_complexity(0, 1);
+                self.nested(|v| visit::visit_expr(v, else_branch));
+            }
         }
 
-        self.coverage.if_stmt_cov.insert(self.current_branch, (i.span().start().line, i.span().start().column, i.span().end().line, i.span().end().column));
+        if !excluded {
+            self.coverage.if_stmt_cov.insert(self.current_branch, (i.span().start().line, i.span().start().column, i.span().end().line, i.span().end().column));
+        }
 
         //visit::visit_expr_if(self, i); 얘는 그냥 if문부터 else 끝까지를 가리킬 때 사용하게 됨
     }
@@ -234,15 +445,23 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
-        self.current_branch += 1;
-        self.coverage.branch_total += 1;
-        self.coverage.branch_cov.insert(self.current_branch, (span_start_line, span_start, span_end_line, span_end));
+        let excluded = self.is_excluded("branch", (span_start_line, span_start, span_end_line, span_end));
+        if !excluded {
+            self.current_branch += 1;
+            self.coverage.branch_total += 1;
+            self.coverage.branch_cov.insert(self.current_branch, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("branch", self.current_branch, &format!("match {}", quote::ToTokens::to_token_stream(&i.expr)));
+        }
+
+        self.add_complexity(i.arms.len().saturating_sub(1), 1 + self.nesting);
 
         visit::visit_expr(self, &i.expr);
         let _ = &i.arms.iter().for_each(|arm| {
-            visit::visit_arm(self, arm);
+            self.nested(|v| visit::visit_arm(v, arm));
         });
-        self.coverage.switch_cov.insert(i.arms.len(), (span_start_line, span_start, span_end_line, span_end));
+        if !excluded {
+            self.coverage.switch_cov.insert(i.arms.len(), (span_start_line, span_start, span_end_line, span_end));
+        }
 
         //syn::visit::visit_expr_match(self, i);
     }
@@ -254,11 +473,15 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
-        self.current_loop += 1;
-        self.coverage.loop_total += 1;
-        self.coverage.loop_cov.insert(self.current_loop, (span_start_line, span_start, span_end_line, span_end));
+        if !self.is_excluded("loop", (span_start_line, span_start, span_end_line, span_end)) {
+            self.current_loop += 1;
+            self.coverage.loop_total += 1;
+            self.coverage.loop_cov.insert(self.current_loop, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("loop", self.current_loop, "loop");
+        }
 
-        visit::visit_expr_loop(self, i);
+        self.add_complexity(0, 1 + self.nesting);
+        self.nested(|v| visit::visit_expr_loop(v, i));
     }
 
     fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
@@ -267,11 +490,15 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
-        self.current_loop += 1;
-        self.coverage.loop_total += 1;
-        self.coverage.loop_cov.insert(self.current_loop, (span_start_line, span_start, span_end_line, span_end));
+        if !self.is_excluded("loop", (span_start_line, span_start, span_end_line, span_end)) {
+            self.current_loop += 1;
+            self.coverage.loop_total += 1;
+            self.coverage.loop_cov.insert(self.current_loop, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("loop", self.current_loop, &format!("while {}", quote::ToTokens::to_token_stream(&i.cond)));
+        }
 
-        visit::visit_expr_while(self, i);
+        self.add_complexity(1, 1 + self.nesting);
+        self.nested(|v| visit::visit_expr_while(v, i));
     }
 
     fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
@@ -280,11 +507,15 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
-        self.current_loop += 1;
-        self.coverage.loop_total += 1;
-        self.coverage.loop_cov.insert(self.current_loop, (span_start_line, span_start, span_end_line, span_end));
+        if !self.is_excluded("loop", (span_start_line, span_start, span_end_line, span_end)) {
+            self.current_loop += 1;
+            self.coverage.loop_total += 1;
+            self.coverage.loop_cov.insert(self.current_loop, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("loop", self.current_loop, &format!("for {} in {}", quote::ToTokens::to_token_stream(&i.pat), quote::ToTokens::to_token_stream(&i.expr)));
+        }
 
-        visit::visit_expr_for_loop(self, i);
+        self.add_complexity(1, 1 + self.nesting);
+        self.nested(|v| visit::visit_expr_for_loop(v, i));
     }
 
     // 얘는 딱 범위만 가리키는 애인듯 하다. loop에 넣으면 X
@@ -303,15 +534,36 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
 
 
 
+    fn visit_expr_closure(&mut self, i: &'ast syn::ExprClosure) {
+        self.nested(|v| visit::visit_expr_closure(v, i));
+    }
+
+    fn visit_expr_break(&mut self, i: &'ast syn::ExprBreak) {
+        if i.label.is_some() {
+            self.add_complexity(0, 1);
+        }
+        visit::visit_expr_break(self, i);
+    }
+
+    fn visit_expr_continue(&mut self, i: &'ast syn::ExprContinue) {
+        if i.label.is_some() {
+            self.add_complexity(0, 1);
+        }
+        visit::visit_expr_continue(self, i);
+    }
+
     fn visit_stmt_macro(&mut self, i: &'ast syn::StmtMacro) {
         let span_start_line = i.span().start().line;
         let span_start = i.span().start().column;
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
-        self.current_macro += 1;
-        self.coverage.macro_total += 1;
-        self.coverage.macro_cov.insert(self.current_macro, (span_start_line, span_start, span_end_line, span_end));
+        if !self.is_excluded("macro", (span_start_line, span_start, span_end_line, span_end)) {
+            self.current_macro += 1;
+            self.coverage.macro_total += 1;
+            self.coverage.macro_cov.insert(self.current_macro, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("macro", self.current_macro, &quote::ToTokens::to_token_stream(&i.mac).to_string());
+        }
 
         visit::visit_stmt_macro(self, i);
     
@@ -323,19 +575,95 @@ impl<'ast> Visit<'ast> for CoverageVisitor {
         let span_end_line = i.span().end().line;
         let span_end = i.span().end().column;
 
-        self.current_macro += 1;
-        self.coverage.macro_total += 1;
-        self.coverage.macro_cov.insert(self.current_macro, (span_start_line, span_start, span_end_line, span_end));
+        if !self.is_excluded("macro", (span_start_line, span_start, span_end_line, span_end)) {
+            self.current_macro += 1;
+            self.coverage.macro_total += 1;
+            self.coverage.macro_cov.insert(self.current_macro, (span_start_line, span_start, span_end_line, span_end));
+            self.stable_id("macro", self.current_macro, &quote::ToTokens::to_token_stream(&i.mac).to_string());
+        }
 
         visit::visit_expr_macro(self, i);
     }
 
 }
 
-fn main() {
-    let contents = fs::read_to_string("example/if-let.rs").expect("Something went wrong reading the file");
+// `&&` / `||` operators of a condition in source order, looking through parentheses.
+fn logical_ops(expr: &Expr) -> Vec<bool> {
+    match expr {
+        Expr::Binary(b) if matches!(b.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) => {
+            let mut ops = logical_ops(&b.left);
+            ops.push(matches!(b.op, syn::BinOp::And(_)));
+            ops.extend(logical_ops(&b.right));
+            ops
+        }
+        Expr::Paren(p) => logical_ops(&p.expr),
+        _ => Vec::new(),
+    }
+}
+
+// A compound statement (`if`, `match`, loops) owns only its header, i.e. the
+// code up to the end of its condition / scrutinee; the nested body is made of
+// statements of its own. So does a `let` bound to a block or to a closure with
+// a block body, up to the opening brace or the closure's parameters. A bare
+// block or a nested item owns nothing and yields None: the item's code is
+// counted in its own function.
+fn stmt_span(s: &Stmt) -> Option<Span> {
+    let start = s.span().start();
+    let header_end = match s {
+        Stmt::Local(local) => match &local.init {
+            Some(init) if init.diverge.is_some() => Some(init.expr.span().end()),
+            Some(init) => header_end(&init.expr),
+            None => None,
+        },
+        Stmt::Expr(Expr::Block(_) | Expr::Unsafe(_), _) | Stmt::Item(_) => return None,
+        Stmt::Expr(expr, _) => header_end(expr),
+        _ => None,
+    };
+    let end = header_end.unwrap_or_else(|| s.span().end());
+
+    Some((start.line, start.column, end.line, end.column))
+}
+
+// Where the part of `expr` evaluated before any nested block ends, None when
+// all of it is. A closure with an expression body is left whole, its body is
+// no statement of its own.
+fn header_end(expr: &Expr) -> Option<proc_macro2::LineColumn> {
+    match expr {
+        Expr::If(i) => Some(i.cond.span().end()),
+        Expr::Match(i) => Some(i.expr.span().end()),
+        Expr::While(i) => Some(i.cond.span().end()),
+        Expr::ForLoop(i) => Some(i.expr.span().end()),
+        Expr::Loop(i) => Some(i.loop_token.span.end()),
+        Expr::Block(b) => Some(b.block.brace_token.span.open().end()),
+        Expr::Unsafe(u) => Some(u.block.brace_token.span.open().end()),
+        Expr::Closure(c) if matches!(c.body.as_ref(), Expr::Block(_)) => match &c.output {
+            syn::ReturnType::Type(_, ty) => Some(ty.span().end()),
+            syn::ReturnType::Default => Some(c.or2_token.span().end()),
+        },
+        _ => None,
+    }
+}
+
+// Source text covered by `span`, with whitespace collapsed onto one line.
+fn snippet(source: &str, span: &Span) -> String {
+    let (start_l, start, end_l, end) = *span;
+    let mut text = String::new();
+    for (idx, line) in source.lines().enumerate().skip(start_l - 1).take(end_l + 1 - start_l) {
+        let from = if idx + 1 == start_l { start } else { 0 };
+        let to = if idx + 1 == end_l { end } else { line.chars().count() };
+        text.push(' ');
+        text.extend(line.chars().skip(from).take(to.saturating_sub(from)));
+    }
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn read_source(path: &str) -> (String, syn::File) {
+    let contents = fs::read_to_string(path).expect("Something went wrong reading the file");
     let syntax = syn::parse_file(&contents).expect("Unable to parse file");
+    (contents, syntax)
+}
 
+fn collect_coverage(contents: &str, syntax: &syn::File) -> Coverage {
     let mut visitor = CoverageVisitor {
         coverage: Coverage::new(),
         current_func: 0,
@@ -345,8 +673,459 @@ fn main() {
         current_loop: 0,
         current_macro: 0,
         current_binary_conditional: 0,
+
+        exclusions: Exclusions::from_source(contents),
+        coverage_off: None,
+
+        fn_stack: Vec::new(),
+        nesting: 0,
+        else_if: false,
+
+        source: contents.to_string(),
+        scope: Vec::new(),
+        seen: BTreeMap::new(),
+    };
+    visitor.visit_file(syntax);
+    visitor.coverage
+}
+
+fn ast_command(path: &str) {
+    let (contents, syntax) = read_source(path);
+    collect_coverage(&contents, &syntax).report();
+}
+
+// rust-cov complexity <file> [<hits>]
+fn complexity_command(args: &[String]) {
+    let path = args.first().expect("Usage: rust-cov complexity <file> [<hits>]");
+    let (contents, syntax) = read_source(path);
+    let hits = args.get(1).map(|hits_path| hits::LineHits::load(hits_path));
+
+    complexity::report(&collect_coverage(&contents, &syntax), hits.as_ref());
+}
+
+// rust-cov cfg <file> [--json]
+fn cfg_command(args: &[String]) {
+    let path = args.first().expect("Usage: rust-cov cfg <file> [--json]");
+    let (_, syntax) = read_source(path);
+    let cfgs = cfg::build_all(&syntax);
+
+    if args.iter().any(|arg| arg == "--json") {
+        println!("{}", serde_json::to_string_pretty(&cfgs).expect("Unable to serialize CFG"));
+    } else {
+        for cfg in &cfgs {
+            print!("{}", cfg.to_dot());
+        }
+    }
+}
+
+// rust-cov edges <file> <hits>
+fn edges_command(args: &[String]) {
+    let [path, hits_path, ..] = args else {
+        panic!("Usage: rust-cov edges <file> <hits>");
+    };
+    let (_, syntax) = read_source(path);
+    let hits = hits::LineHits::load(hits_path);
+    let cfgs = cfg::build_all(&syntax);
+    let coverages: Vec<edge::EdgeCoverage> = cfgs.iter().map(|cfg| edge::EdgeCoverage::new(cfg, &hits)).collect();
+
+    edge::report(&file_name(path), &coverages);
+}
+
+// rust-cov paths <file> [<traces>] [--loop-cap N]
+fn paths_command(args: &[String]) {
+    let path = args.first().expect("Usage: rust-cov paths <file> [<traces>] [--loop-cap N]");
+    let loop_cap = flag_value(args, "--loop-cap").map_or(1, |v| v.parse().expect("--loop-cap expects a number"));
+    let traces = match args.get(1).filter(|arg| !arg.starts_with("--")) {
+        Some(traces_path) => path::load_traces(traces_path),
+        None => Vec::new(),
+    };
+    let (_, syntax) = read_source(path);
+
+    path::report(&path::without_tests(&syntax, cfg::build_all(&syntax)), &traces, loop_cap);
+}
+
+// rust-cov dataflow <file> [<traces>]
+fn dataflow_command(args: &[String]) {
+    let path = args.first().expect("Usage: rust-cov dataflow <file> [<traces>]");
+    let traces = args.get(1).map_or_else(Vec::new, |traces_path| path::load_traces(traces_path));
+    let (contents, syntax) = read_source(path);
+
+    dataflow::report(&contents, &dataflow::analyze_all(&syntax), &traces);
+}
+
+// rust-cov mutate <file>
+fn mutate_command(args: &[String]) {
+    let path = args.first().expect("Usage: rust-cov mutate <file>");
+    let (contents, syntax) = read_source(path);
+    let mutants = mutation::mutate(&contents, &syntax);
+    let equivalent = equivalent::detect(&contents, &syntax, &mutants);
+
+    for mutant in mutants.iter().filter(|m| !equivalent.contains_key(&m.id)) {
+        println!("{}", mutant);
+        print!("{}", mutation::diff(path, &contents, mutant));
+    }
+    if !equivalent.is_empty() {
+        println!("Equivalent mutants ({}):", equivalent.len());
+        for mutant in mutants.iter().filter(|m| equivalent.contains_key(&m.id)) {
+            println!("  {} ({})", mutant, equivalent[&mutant.id]);
+        }
+    }
+}
+
+// rust-cov mutest <crate-dir> <file> [--timeout SECS] [--out FILE] [--schemata] [--hits FILE] [--traces FILE]
+fn mutest_command(args: &[String]) {
+    let usage = "Usage: rust-cov mutest <crate-dir> <file> [--timeout SECS] [--out FILE] [--schemata] \
+                 [--hits FILE] [--traces FILE]";
+    let crate_dir = args.first().expect(usage);
+    let file = args.get(1).expect(usage);
+    let timeout = flag_value(args, "--timeout").map_or(60, |secs| secs.parse().expect(usage));
+    let out = flag_value(args, "--out").map_or("mutest.json", String::as_str);
+
+    let sandbox = runner::Sandbox::new(crate_dir, file, std::time::Duration::from_secs(timeout));
+    let syntax = syn::parse_file(sandbox.source()).expect("Unable to parse file");
+    let mutants = mutation::mutate(sandbox.source(), &syntax);
+    let equivalent = equivalent::detect(sandbox.source(), &syntax, &mutants);
+
+    let coverage = runner::MutantCoverage {
+        hits: flag_value(args, "--hits").map(|path| hits::LineHits::load(path)),
+        traces: flag_value(args, "--traces").map_or(Vec::new(), |path| path::load_traces(path)),
+    };
+
+    let schemata = args.iter().any(|arg| arg == "--schemata");
+    let results = runner::execute(&sandbox, file, &mutants, out, schemata, &coverage, &equivalent);
+    let summary = runner::summary(&results);
+    fs::write(std::path::Path::new(out).with_extension("txt"), &summary).expect("Something went wrong writing the summary");
+    print!("{}", summary);
+}
+
+// rust-cov trace <crate-dir> <file> [--timeout SECS] [--out FILE]
+fn trace_command(args: &[String]) {
+    let usage = "Usage: rust-cov trace <crate-dir> <file> [--timeout SECS] [--out FILE]";
+    let crate_dir = args.first().expect(usage);
+    let file = args.get(1).expect(usage);
+    let timeout = flag_value(args, "--timeout").map_or(60, |secs| secs.parse().expect(usage));
+    let out = flag_value(args, "--out").map_or("traces.txt", String::as_str);
+
+    let sandbox = runner::Sandbox::new(crate_dir, file, std::time::Duration::from_secs(timeout));
+    let mut rows = String::new();
+    for (name, tokens, status) in probe::collect(&sandbox) {
+        let outcome = match status {
+            runner::Status::Survived => "ok".to_string(),
+            runner::Status::Timeout => "timeout".to_string(),
+            _ => "FAILED".to_string(),
+        };
+        let lines = tokens.iter().filter(|token| token.parse::<usize>().is_ok()).count();
+        println!("{} ... {} ({} lines traced)", name, outcome, lines);
+        rows += &format!("{}: {}\n", name, tokens.join(" "));
+    }
+    fs::write(out, rows).expect("Something went wrong writing the traces file");
+}
+
+// rust-cov attribute <file> <traces>
+fn attribute_command(args: &[String]) {
+    let [path, traces_path, ..] = args else {
+        panic!("Usage: rust-cov attribute <file> <traces>");
     };
-    visitor.visit_file(&syntax);
+    let (contents, syntax) = read_source(path);
+    let traces = path::load_traces(traces_path);
 
-    visitor.coverage.report();
+    attribution::report(&contents, &syntax, &collect_coverage(&contents, &syntax), &cfg::build_all(&syntax), &traces);
+}
+
+// rust-cov affected <file> <traces> [--git REV | --diff PATCH | --trees OLD NEW]
+fn affected_command(args: &[String]) {
+    let [path, traces_path, ..] = args else {
+        panic!("Usage: rust-cov affected <file> <traces> [--git REV | --diff PATCH | --trees OLD NEW]");
+    };
+    // the traces belong to the old side of the diff: the file as of REV, or as on disk
+    let (contents, diff) = match changes_diff(args) {
+        Some(diff) => (fs::read_to_string(path).expect("Something went wrong reading the file"), diff),
+        None => changes::from_git(path, flag_value(args, "--git").map_or("HEAD", String::as_str)),
+    };
+    let syntax = syn::parse_file(&contents).expect("Unable to parse file");
+    let traces = path::load_traces(traces_path);
+
+    let changes = changes::parse(&diff, path);
+    changes::report(&contents, &syntax, &collect_coverage(&contents, &syntax), &traces, &changes);
+}
+
+// rust-cov patch <file> <hits> [--git REV | --diff PATCH | --trees OLD NEW] [--fail-under PCT]
+fn patch_command(args: &[String]) {
+    let [path, hits_path, ..] = args else {
+        panic!("Usage: rust-cov patch <file> <hits> [--git REV | --diff PATCH | --trees OLD NEW] [--fail-under PCT]");
+    };
+    let threshold = flag_value(args, "--fail-under").map(|v| v.parse().expect("--fail-under expects a percentage"));
+    // the hits belong to the new side of the diff, the file as on disk
+    let (contents, syntax) = read_source(path);
+    let diff = changes_diff(args).unwrap_or_else(|| changes::from_git(path, flag_value(args, "--git").map_or("HEAD", String::as_str)).1);
+    let hits = hits::LineHits::load(hits_path);
+
+    let changes = changes::parse(&diff, path);
+    let coverage = collect_coverage(&contents, &syntax);
+    let failed = patch::report(&file_name(path), &contents, &coverage, &cfg::build_all(&syntax), &hits, &changes, threshold);
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+// The diff given by `--diff PATCH` or `--trees OLD NEW`.
+fn changes_diff(args: &[String]) -> Option<String> {
+    if let Some(patch) = flag_value(args, "--diff") {
+        return Some(fs::read_to_string(patch).expect("Something went wrong reading the diff"));
+    }
+    let idx = args.iter().position(|arg| arg == "--trees")?;
+    let (Some(old), Some(new)) = (args.get(idx + 1), args.get(idx + 2)) else {
+        panic!("Usage: --trees OLD NEW");
+    };
+    Some(changes::from_trees(old, new))
+}
+
+// rust-cov cobertura <file> <hits> [<file> <hits> ...] [--traces FILE] [--out FILE]
+fn cobertura_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov cobertura <file> <hits> [<file> <hits> ...] [--traces FILE] [--out FILE]");
+    let out = flag_value(args, "--out").map_or("coverage.xml", String::as_str);
+
+    fs::write(out, cobertura::report(&files)).expect("Something went wrong writing the report");
+    println!("Cobertura report written to {}", out);
+}
+
+// rust-cov gate <file> <hits> [<file> <hits> ...] [--traces FILE] [--config FILE]
+//               [--functions PCT] [--statements PCT] [--branches PCT] [--mcdc PCT]
+fn gate_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov gate <file> <hits> [<file> <hits> ...] [--traces FILE] [--config FILE] \
+                                      [--functions PCT] [--statements PCT] [--branches PCT] [--mcdc PCT]");
+    // rust-cov.toml is read when present, the flags override its global thresholds
+    let config = match flag_value(args, "--config") {
+        Some(path) => gate::Config::load(path),
+        None if std::path::Path::new("rust-cov.toml").exists() => gate::Config::load("rust-cov.toml"),
+        None => gate::Config::default(),
+    };
+    let mut rules = config.thresholds;
+    let percent = |flag: &str| flag_value(args, flag).map(|v| v.parse::<f64>().unwrap_or_else(|_| panic!("{} expects a percentage", flag)));
+    let global = &mut rules.global;
+    for (flag, threshold) in [("--functions", &mut global.functions), ("--statements", &mut global.statements),
+                              ("--branches", &mut global.branches), ("--mcdc", &mut global.mcdc)] {
+        if let Some(value) = percent(flag) {
+            *threshold = Some(value);
+        }
+    }
+
+    let mcdc = flag_value(args, "--traces").map(|traces| {
+        let (_, syntax) = read_source(&args[0]);
+        let conditions = mcdc::conditions(&cfg::build_all(&syntax), &path::load_rows(traces));
+        (conditions.iter().filter(|condition| !condition.pairs.is_empty()).count(), conditions.len())
+    });
+    if mcdc.is_none() && (rules.global.mcdc.is_some() || rules.paths.iter().any(|rule| rule.thresholds.mcdc.is_some())) {
+        println!("MC/DC is not checked without --traces");
+    }
+
+    let violations = gate::check(&files, mcdc, &rules);
+    if violations.is_empty() {
+        println!("Coverage meets every threshold");
+        return;
+    }
+    println!("Coverage below its thresholds ({} violations):", violations.len());
+    for violation in &violations {
+        println!("- {}", violation);
+    }
+    std::process::exit(1);
+}
+
+// rust-cov html <file> <hits> [<file> <hits> ...] [--traces FILE] [--out DIR]
+fn html_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov html <file> <hits> [<file> <hits> ...] [--traces FILE] [--out DIR]");
+    let out = flag_value(args, "--out").map_or("coverage-html", String::as_str);
+
+    let pages = html::write(out, &files);
+    println!("HTML report written to {}", pages[0]);
+}
+
+// rust-cov annotate <file> <hits> [<file> <hits> ...] [--traces FILE]
+fn annotate_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov annotate <file> <hits> [<file> <hits> ...] [--traces FILE]");
+
+    annotate::print(&files);
+}
+
+// rust-cov sarif <file> <hits> [<file> <hits> ...] [--traces FILE] [--mutants RESULTS] [--out FILE]
+fn sarif_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov sarif <file> <hits> [<file> <hits> ...] [--traces FILE] [--mutants RESULTS] [--out FILE]");
+    let out = flag_value(args, "--out").map_or("rust-cov.sarif", String::as_str);
+    let mutants = flag_value(args, "--mutants")
+        .map(|path| runner::Results::load(path).expect("Something went wrong reading the mutation results"));
+
+    let log = sarif::log(&files, mutants.as_ref());
+    fs::write(out, serde_json::to_string_pretty(&log).expect("Unable to serialize SARIF log"))
+        .expect("Something went wrong writing the SARIF log");
+    println!("SARIF log written to {}", out);
+}
+
+// rust-cov remap <old file> <old hits> <file> [--out FILE]
+fn remap_command(args: &[String]) {
+    let [old_path, hits_path, path, ..] = args else {
+        panic!("Usage: rust-cov remap <old file> <old hits> <file> [--out FILE]");
+    };
+    let out = flag_value(args, "--out").map_or("remapped-hits.txt", String::as_str);
+    let (old_contents, old_syntax) = read_source(old_path);
+    let (contents, syntax) = read_source(path);
+    let old = collect_coverage(&old_contents, &old_syntax);
+
+    let (counts, lost) = identity::remap(&old, &hits::LineHits::load(hits_path), &collect_coverage(&contents, &syntax));
+    let rows: String = counts.iter().map(|(line, count)| format!("{} {}\n", line, count)).collect();
+    fs::write(out, rows).expect("Something went wrong writing the hits file");
+    println!("Mapped {} of {} items, hits written to {}", old.ids.len() - lost.len(), old.ids.len(), out);
+    if !lost.is_empty() {
+        println!("Changed or removed since {}:", old_path);
+        for id in &lost {
+            println!("- {}", id);
+        }
+    }
+}
+
+// rust-cov snapshot <file> <hits> [<file> <hits> ...] [--traces FILE] [--out FILE]
+fn snapshot_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov snapshot <file> <hits> [<file> <hits> ...] [--traces FILE] [--out FILE]");
+    let out = flag_value(args, "--out").map_or("rust-cov-baseline.json", String::as_str);
+
+    baseline::Snapshot::new(&files).save(out);
+    println!("Coverage snapshot written to {}", out);
+}
+
+// rust-cov regressions <file> <hits> [<file> <hits> ...] [--traces FILE] [--baseline FILE]
+fn regressions_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov regressions <file> <hits> [<file> <hits> ...] [--traces FILE] [--baseline FILE]");
+    let path = flag_value(args, "--baseline").map_or("rust-cov-baseline.json", String::as_str);
+    let snapshot = baseline::Snapshot::load(path).expect("Something went wrong reading the baseline");
+
+    if baseline::compare(&snapshot, &files) {
+        std::process::exit(1);
+    }
+}
+
+// The `<file> <hits>` pairs leading the arguments. Decision evaluations from
+// `--traces` belong to a single traced file.
+fn file_coverages(args: &[String], usage: &str) -> Vec<summary::FileCoverage> {
+    let inputs: Vec<&String> = args.iter().take_while(|arg| !arg.starts_with("--")).collect();
+    if inputs.is_empty() || !inputs.len().is_multiple_of(2) {
+        panic!("{}", usage);
+    }
+    let conditions = flag_value(args, "--traces").map(|traces| mcdc::condition_counts(&path::load_rows(traces)));
+    if conditions.is_some() && inputs.len() > 2 {
+        panic!("--traces applies to a single file");
+    }
+    inputs.chunks(2)
+        .map(|pair| {
+            let (contents, syntax) = read_source(pair[0]);
+            let hits = hits::LineHits::load(pair[1]);
+            let coverage = collect_coverage(&contents, &syntax);
+            summary::FileCoverage::new(pair[0], &contents, &coverage, &cfg::build_all(&syntax), &hits, conditions.as_ref())
+        })
+        .collect()
+}
+
+// rust-cov lcov <file> <hits> [--traces FILE] [--out FILE]
+fn lcov_command(args: &[String]) {
+    let [path, hits_path, ..] = args else {
+        panic!("Usage: rust-cov lcov <file> <hits> [--traces FILE] [--out FILE]");
+    };
+    let out = flag_value(args, "--out").map_or("lcov.info", String::as_str);
+    let (contents, syntax) = read_source(path);
+    let hits = hits::LineHits::load(hits_path);
+    let conditions = flag_value(args, "--traces").map(|traces| mcdc::condition_counts(&path::load_rows(traces)));
+
+    let coverage = collect_coverage(&contents, &syntax);
+    let file = summary::FileCoverage::new(path, &contents, &coverage, &cfg::build_all(&syntax), &hits, conditions.as_ref());
+    fs::write(out, lcov::tracefile(&file)).expect("Something went wrong writing the tracefile");
+    println!("LCOV tracefile written to {}", out);
+}
+
+// rust-cov markdown <file> <hits> [<file> <hits> ...] [--traces FILE] [--baseline FILE] [--save FILE] [--link URL] [--out FILE]
+fn markdown_command(args: &[String]) {
+    let files = file_coverages(args, "Usage: rust-cov markdown <file> <hits> [<file> <hits> ...] [--traces FILE] [--baseline FILE] [--save FILE] [--link URL] [--out FILE]");
+    let baseline = flag_value(args, "--baseline")
+        .map(|path| baseline::Snapshot::load(path).expect("Something went wrong reading the baseline"));
+
+    let report = markdown::report(&files, baseline.as_ref(), flag_value(args, "--link").map(String::as_str));
+    match flag_value(args, "--out") {
+        Some(out) => {
+            fs::write(out, report).expect("Something went wrong writing the report");
+            println!("Markdown report written to {}", out);
+        }
+        None => print!("{}", report),
+    }
+    if let Some(path) = flag_value(args, "--save") {
+        baseline::Snapshot::new(&files).save(path);
+    }
+}
+
+// rust-cov minimize <file> <traces> [--criterion stmt|branch|mcdc]
+fn minimize_command(args: &[String]) {
+    let [path, traces_path, ..] = args else {
+        panic!("Usage: rust-cov minimize <file> <traces> [--criterion stmt|branch|mcdc]");
+    };
+    let criterion = flag_value(args, "--criterion").map_or("branch", String::as_str);
+    let (contents, syntax) = read_source(path);
+    let cfgs = cfg::build_all(&syntax);
+    let rows = path::load_rows(traces_path);
+    let names: Vec<String> = rows.iter().map(|(name, _)| name.clone()).collect();
+
+    let requirements = match criterion {
+        "stmt" | "branch" => {
+            let traces = path::load_traces(traces_path);
+            let items = attribution::items(&contents, &syntax, &collect_coverage(&contents, &syntax), &cfgs, &traces);
+            let items = if criterion == "stmt" { items.statements } else { items.branches };
+            items.into_iter()
+                .map(|item| minimize::Requirement {
+                    label: item.label,
+                    alternatives: names.iter().enumerate().filter(|(_, name)| item.tests.contains(*name)).map(|(idx, _)| BTreeSet::from([idx])).collect(),
+                })
+                .collect()
+        }
+        "mcdc" => mcdc::conditions(&cfgs, &rows).into_iter()
+            .map(|condition| minimize::Requirement {
+                label: condition.label,
+                alternatives: condition.pairs.into_iter().map(|(a, b)| BTreeSet::from([a, b])).collect(),
+            })
+            .collect(),
+        _ => panic!("Unknown criterion {}, expected stmt, branch or mcdc", criterion),
+    };
+    minimize::report(criterion, &names, requirements);
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter().position(|arg| arg == flag).and_then(|idx| args.get(idx + 1))
+}
+
+fn file_name(path: &str) -> String {
+    std::path::Path::new(path).file_name().map_or(path.to_string(), |name| name.to_string_lossy().into_owned())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("affected") => affected_command(&args[1..]),
+        Some("annotate") => annotate_command(&args[1..]),
+        Some("attribute") => attribute_command(&args[1..]),
+        Some("cfg") => cfg_command(&args[1..]),
+        Some("cobertura") => cobertura_command(&args[1..]),
+        Some("complexity") => complexity_command(&args[1..]),
+        Some("dataflow") => dataflow_command(&args[1..]),
+        Some("edges") => edges_command(&args[1..]),
+        Some("gate") => gate_command(&args[1..]),
+        Some("html") => html_command(&args[1..]),
+        Some("lcov") => lcov_command(&args[1..]),
+        Some("markdown") => markdown_command(&args[1..]),
+        Some("minimize") => minimize_command(&args[1..]),
+        Some("mutate") => mutate_command(&args[1..]),
+        Some("mutest") => mutest_command(&args[1..]),
+        Some("patch") => patch_command(&args[1..]),
+        Some("paths") => paths_command(&args[1..]),
+        Some("regressions") => regressions_command(&args[1..]),
+        Some("remap") => remap_command(&args[1..]),
+        Some("sarif") => sarif_command(&args[1..]),
+        Some("snapshot") => snapshot_command(&args[1..]),
+        Some("trace") => trace_command(&args[1..]),
+        Some(path) => ast_command(path),
+        None => ast_command("example/if-let.rs"),
+    }
 }
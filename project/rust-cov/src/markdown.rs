@@ -0,0 +1,100 @@
+use crate::baseline::{Snapshot, Totals};
+use crate::summary::FileCoverage;
+
+// A table with the function, statement, branch and loop coverage of every
+// file, with the change since `baseline`, followed by the uncovered items in
+// a collapsed list. Locations link to `link_base/<path>#L<line>` when given.
+pub fn report(files: &[FileCoverage], baseline: Option<&Snapshot>, link_base: Option<&str>) -> String {
+    let mut text = String::from("## Coverage\n\n| File | Functions | Statements | Branches | Loops |\n|:-----|----------:|-----------:|---------:|------:|\n");
+    let mut total = Totals::default();
+    for file in files {
+        let totals = Totals::of(file);
+        total.add(&totals);
+        let before = baseline.and_then(|baseline| baseline.files.get(&file.path).map(|file| &file.totals));
+        text += &row(&format!("`{}`", file.path), &totals, baseline.map(|_| before));
+    }
+    if files.len() > 1 {
+        let before = baseline.map(Snapshot::total);
+        text += &row("**Total**", &total, before.as_ref().map(Some));
+    }
+
+    let uncovered: Vec<String> = files.iter().flat_map(|file| uncovered(file, link_base)).collect();
+    if !uncovered.is_empty() {
+        text += &format!("\n<details>\n<summary>Uncovered items ({})</summary>\n\n", uncovered.len());
+        for item in &uncovered {
+            text += &format!("- {}\n", item);
+        }
+        text += "\n</details>\n";
+    }
+    text
+}
+
+// `before` is `None` without a baseline, `Some(None)` for a file new since it.
+fn row(name: &str, totals: &Totals, before: Option<Option<&Totals>>) -> String {
+    let cell = |ratio: (usize, usize), old: Option<(usize, usize)>| {
+        if ratio.1 == 0 {
+            return "–".to_string();
+        }
+        let delta = match before {
+            None => String::new(),
+            Some(None) => " 🆕".to_string(),
+            Some(Some(_)) => delta(percent(ratio) - percent(old.unwrap_or_default())),
+        };
+        format!("{:.2}% ({}/{}){}", percent(ratio), ratio.0, ratio.1, delta)
+    };
+    let old = before.flatten();
+    format!("| {} | {} | {} | {} | {} |\n", name,
+            cell(totals.functions, old.map(|t| t.functions)),
+            cell(totals.statements, old.map(|t| t.statements)),
+            cell(totals.branches, old.map(|t| t.branches)),
+            cell(totals.loops, old.map(|t| t.loops)))
+}
+
+fn delta(change: f64) -> String {
+    if change.abs() < 0.005 {
+        String::new()
+    } else if change > 0.0 {
+        format!(" ▲ +{:.2}", change)
+    } else {
+        format!(" ▼ {:.2}", change)
+    }
+}
+
+fn uncovered(file: &FileCoverage, link_base: Option<&str>) -> Vec<String> {
+    let location = |line: usize| match link_base {
+        Some(base) => format!("[`{}:{}`]({}/{}#L{})", file.path, line, base.trim_end_matches('/'), file.path.trim_start_matches("./"), line),
+        None => format!("`{}:{}`", file.path, line),
+    };
+    // (line, description), listed in source order
+    let mut items: Vec<(usize, String)> = Vec::new();
+    for function in file.functions.iter().filter(|f| f.hits == 0) {
+        items.push((function.span.0, format!("function `{}` never called", function.name)));
+    }
+    for (span, _) in file.statements.iter().filter(|(_, count)| *count == 0) {
+        // a function never called has every one of its statements uncovered
+        if !file.functions.iter().any(|f| f.hits == 0 && (f.span.0..=f.span.2).contains(&span.0)) {
+            items.push((span.0, "statement never executed".to_string()));
+        }
+    }
+    for branch in file.branches.iter().filter(|branch| branch.covered() < branch.arms.len()) {
+        let missed: Vec<String> = branch.arm_names.iter().zip(&branch.arms)
+            .filter(|(_, taken)| taken.unwrap_or(0) == 0)
+            .map(|(name, _)| format!("`{}`", escape(name)))
+            .collect();
+        items.push((branch.line, format!("`{}`: {} never taken", escape(&branch.text), missed.join(", "))));
+    }
+    for (span, _) in file.loops.iter().filter(|(_, count)| *count == 0) {
+        items.push((span.0, "loop never entered".to_string()));
+    }
+    items.sort_by_key(|(line, _)| *line);
+    items.into_iter().map(|(line, description)| format!("{} {}", location(line), description)).collect()
+}
+
+fn percent((covered, total): (usize, usize)) -> f64 {
+    if total == 0 { 100.0 } else { covered as f64 / total as f64 * 100.0 }
+}
+
+// A backtick would end the code span.
+fn escape(text: &str) -> String {
+    text.replace('`', "'")
+}
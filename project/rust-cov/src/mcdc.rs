@@ -0,0 +1,114 @@
+use std::collections::{BTreeMap, BTreeSet};
+use quote::ToTokens;
+
+use crate::cfg::Cfg;
+use crate::probe;
+
+// One evaluation of a decision: the value of each condition (`None` when
+// short-circuited) and the outcome.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Evaluation {
+    pub values: Vec<Option<bool>>,
+    pub outcome: bool,
+}
+
+// The decision evaluations among the tokens of a trace (`@line.col=TF-/T`),
+// keyed by the position of the decision.
+pub fn evaluations(tokens: &[String]) -> BTreeMap<(usize, usize), BTreeSet<Evaluation>> {
+    let mut decisions: BTreeMap<(usize, usize), BTreeSet<Evaluation>> = BTreeMap::new();
+    for (at, evaluation) in tokens.iter().filter_map(|token| parse(token)) {
+        decisions.entry(at).or_default().insert(evaluation);
+    }
+    decisions
+}
+
+// How often each condition of a decision was true and false, by decision.
+pub type ConditionCounts = BTreeMap<(usize, usize), Vec<[u64; 2]>>;
+
+// The condition counts over all evaluations in the traces.
+pub fn condition_counts(rows: &[(String, Vec<String>)]) -> ConditionCounts {
+    let mut counts = ConditionCounts::new();
+    for (at, evaluation) in rows.iter().flat_map(|(_, tokens)| tokens.iter().filter_map(|token| parse(token))) {
+        let decision = counts.entry(at).or_insert_with(|| vec![[0, 0]; evaluation.values.len()]);
+        for (count, value) in decision.iter_mut().zip(&evaluation.values) {
+            match value {
+                Some(true) => count[0] += 1,
+                Some(false) => count[1] += 1,
+                None => {}
+            }
+        }
+    }
+    counts
+}
+
+fn parse(token: &str) -> Option<((usize, usize), Evaluation)> {
+    let (at, evaluation) = token.strip_prefix('@')?.split_once('=')?;
+    let (line, col) = at.split_once('.')?;
+    let (values, outcome) = evaluation.split_once('/')?;
+    let values = values.chars()
+        .map(|c| match c {
+            'T' => Some(true),
+            'F' => Some(false),
+            _ => None,
+        })
+        .collect();
+    Some(((line.parse().ok()?, col.parse().ok()?), Evaluation { values, outcome: outcome == "T" }))
+}
+
+// Whether two evaluations show that condition `idx` independently affects the
+// outcome: it flips along with the outcome while every other condition keeps
+// its value or is short-circuited in one of them.
+pub fn shows_independence(a: &Evaluation, b: &Evaluation, idx: usize) -> bool {
+    let flips = matches!((a.values.get(idx), b.values.get(idx)), (Some(Some(x)), Some(Some(y))) if x != y);
+    flips && a.outcome != b.outcome && a.values.len() == b.values.len()
+        && a.values.iter().zip(&b.values).enumerate().all(|(i, (x, y))| i == idx || x.is_none() || y.is_none() || x == y)
+}
+
+// A condition of a decision and the pairs of tests (by index, possibly the same
+// test twice) whose evaluations show its independence.
+pub struct Condition {
+    pub label: String,
+    pub pairs: BTreeSet<(usize, usize)>,
+}
+
+// The conditions of every decision evaluated by the tests, given as the token
+// rows of a traces file.
+pub fn conditions(cfgs: &[Cfg], tests: &[(String, Vec<String>)]) -> Vec<Condition> {
+    let recorded: Vec<BTreeMap<(usize, usize), BTreeSet<Evaluation>>> = tests.iter().map(|(_, tokens)| evaluations(tokens)).collect();
+    let decisions: BTreeMap<(usize, usize), usize> = recorded.iter()
+        .flat_map(|decisions| decisions.iter().flat_map(|(at, evals)| evals.iter().map(move |e| (*at, e.values.len()))))
+        .collect();
+    let texts: BTreeMap<(usize, usize), &String> = cfgs.iter()
+        .flat_map(|cfg| cfg.edges.iter().filter_map(|edge| edge.cond.as_ref()))
+        .map(|(span, text)| ((span.0, span.1), text))
+        .collect();
+
+    let mut all = Vec::new();
+    for (&at, &count) in &decisions {
+        let decision = texts.get(&at).map_or(String::new(), |text| text.to_string());
+        let names: Vec<String> = syn::parse_str::<syn::Expr>(&decision).ok()
+            .map(|expr| probe::conditions(&expr).iter().map(|c| c.to_token_stream().to_string()).collect())
+            .unwrap_or_default();
+        for idx in 0..count {
+            let mut pairs = BTreeSet::new();
+            for (a, a_evals) in recorded.iter().enumerate() {
+                for (b, b_evals) in recorded.iter().enumerate().skip(a) {
+                    let (Some(a_evals), Some(b_evals)) = (a_evals.get(&at), b_evals.get(&at)) else {
+                        continue;
+                    };
+                    if a_evals.iter().any(|x| b_evals.iter().any(|y| shows_independence(x, y, idx))) {
+                        pairs.insert((a, b));
+                    }
+                }
+            }
+            let condition = names.get(idx).cloned().unwrap_or_else(|| format!("condition {}", idx + 1));
+            let label = if count > 1 {
+                format!("{}:{} `{}` in `{}`", at.0, at.1, condition, decision)
+            } else {
+                format!("{}:{} `{}`", at.0, at.1, condition)
+            };
+            all.push(Condition { label, pairs });
+        }
+    }
+    all
+}
@@ -0,0 +1,122 @@
+use std::collections::BTreeSet;
+
+// Branch and bound gives up after visiting this many selections.
+const SEARCH_LIMIT: usize = 1_000_000;
+
+// A coverage requirement, met once every test of one of its alternatives is
+// selected: a single test for statements and branches, a pair of tests for an
+// MC/DC condition.
+pub struct Requirement {
+    pub label: String,
+    pub alternatives: Vec<BTreeSet<usize>>,
+}
+
+impl Requirement {
+    fn met(&self, selected: &BTreeSet<usize>) -> bool {
+        self.alternatives.iter().any(|alt| alt.is_subset(selected))
+    }
+}
+
+// Repeatedly adds the tests meeting the most open requirements per added test.
+pub fn greedy(requirements: &[Requirement]) -> BTreeSet<usize> {
+    let mut selected = BTreeSet::new();
+    loop {
+        let open: Vec<&Requirement> = requirements.iter().filter(|r| !r.met(&selected)).collect();
+        let candidates: BTreeSet<Vec<usize>> = open.iter()
+            .flat_map(|r| r.alternatives.iter().map(|alt| alt.difference(&selected).copied().collect()))
+            .collect();
+        let best = candidates.into_iter()
+            .map(|added: Vec<usize>| {
+                let with: BTreeSet<usize> = selected.union(&added.iter().copied().collect()).copied().collect();
+                let gain = open.iter().filter(|r| r.met(&with)).count();
+                (gain as f64 / added.len() as f64, std::cmp::Reverse(added.len()), std::cmp::Reverse(added))
+            })
+            .max_by(|a, b| a.partial_cmp(b).expect("Something went wrong comparing gains"));
+        match best {
+            Some((_, _, std::cmp::Reverse(added))) => selected.extend(added),
+            None => return selected,
+        }
+    }
+}
+
+// The smallest selection meeting every requirement, as a set cover solved by
+// branch and bound: branch on the open requirement with the fewest
+// alternatives, prune selections no smaller than the best found, starting from
+// `bound`. Returns whether the search completed, i.e. the result is optimal.
+pub fn exact(requirements: &[Requirement], bound: BTreeSet<usize>) -> (BTreeSet<usize>, bool) {
+    let mut search = Search { requirements, best: bound, nodes: 0 };
+    search.branch(BTreeSet::new());
+    let complete = search.nodes <= SEARCH_LIMIT;
+    (search.best, complete)
+}
+
+struct Search<'a> {
+    requirements: &'a [Requirement],
+    best: BTreeSet<usize>,
+    nodes: usize,
+}
+
+impl Search<'_> {
+    fn branch(&mut self, selected: BTreeSet<usize>) {
+        self.nodes += 1;
+        if self.nodes > SEARCH_LIMIT {
+            return;
+        }
+        let Some(open) = self.requirements.iter()
+            .filter(|r| !r.met(&selected))
+            .min_by_key(|r| r.alternatives.len()) else {
+            if selected.len() < self.best.len() {
+                self.best = selected;
+            }
+            return;
+        };
+        let mut choices: Vec<BTreeSet<usize>> = open.alternatives.iter().map(|alt| selected.union(alt).copied().collect()).collect();
+        choices.sort_by_key(|choice| choice.len());
+        for choice in choices {
+            if choice.len() < self.best.len() {
+                self.branch(choice);
+            }
+        }
+    }
+}
+
+// Prints the greedy and exact selections, and the tests the exact one leaves
+// out, with a test covering at least the same single-test requirements.
+pub fn report(criterion: &str, names: &[String], requirements: Vec<Requirement>) {
+    let (coverable, uncovered): (Vec<Requirement>, Vec<Requirement>) = requirements.into_iter().partition(|r| !r.alternatives.is_empty());
+    let greedy = greedy(&coverable);
+    let (exact, optimal) = exact(&coverable, greedy.clone());
+
+    println!("Test suite minimization ({} coverage, {} requirements, {} not covered by any test):",
+             criterion, coverable.len(), uncovered.len());
+    let solver = if optimal { "optimal" } else { "search limit reached" };
+    for (method, selection) in [("greedy".to_string(), &greedy), (format!("set cover, {}", solver), &exact)] {
+        println!("- {}: {} of {} tests", method, selection.len(), names.len());
+        for test in selection {
+            println!("  - {}", names[*test]);
+        }
+    }
+
+    // requirements each test meets on its own
+    let alone: Vec<BTreeSet<usize>> = (0..names.len())
+        .map(|test| {
+            let single = BTreeSet::from([test]);
+            coverable.iter().enumerate().filter(|(_, r)| r.met(&single)).map(|(idx, _)| idx).collect()
+        })
+        .collect();
+    let redundant: Vec<usize> = (0..names.len()).filter(|test| !exact.contains(test)).collect();
+    println!("- redundant tests: {}", redundant.len());
+    for test in redundant {
+        let subsumed = (0..names.len())
+            .filter(|other| *other != test && !alone[test].is_empty() && alone[test].is_subset(&alone[*other]))
+            .min_by_key(|other| (alone[*other] != alone[test], !exact.contains(other)));
+        match subsumed {
+            Some(other) if alone[other] == alone[test] => println!("  - {} (same as {})", names[test], names[other]),
+            Some(other) => println!("  - {} (subsumed by {})", names[test], names[other]),
+            None => println!("  - {}", names[test]),
+        }
+    }
+    for requirement in uncovered {
+        println!("- not covered: {}", requirement.label);
+    }
+}
@@ -0,0 +1,297 @@
+use std::fmt;
+use serde::{Deserialize, Serialize};
+use syn::{visit::{self, Visit}, BinOp, Expr, Lit, UnOp};
+
+use crate::cfg::span_of;
+use crate::{snippet, Span};
+
+// longer originals of these kinds are abbreviated like js-mutest does
+const MAX_LEN: usize = 50;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MutantType {
+    Arith,
+    Assign,
+    Block,
+    Cond,
+    Literal,
+    Logical,
+    Relational,
+    Unary,
+}
+
+pub const ALL_MUTANT_TYPES: [MutantType; 8] = [
+    MutantType::Arith,
+    MutantType::Assign,
+    MutantType::Block,
+    MutantType::Cond,
+    MutantType::Literal,
+    MutantType::Logical,
+    MutantType::Relational,
+    MutantType::Unary,
+];
+
+impl fmt::Display for MutantType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            MutantType::Arith => "arith",
+            MutantType::Assign => "assign",
+            MutantType::Block => "block",
+            MutantType::Cond => "cond",
+            MutantType::Literal => "literal",
+            MutantType::Logical => "logical",
+            MutantType::Relational => "relational",
+            MutantType::Unary => "unary",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Mutant {
+    pub id: usize,
+    pub kind: MutantType,
+    // the mutated expression / block, as shown to the user
+    pub span: Span,
+    pub before: String,
+    pub after: String,
+    // the source range actually rewritten and its replacement
+    pub edit: (Span, String),
+}
+
+impl fmt::Display for Mutant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (start_l, start, end_l, end) = self.span;
+        let before = if self.before.len() > MAX_LEN && self.kind == MutantType::Block {
+            "{ ... }"
+        } else {
+            &self.before
+        };
+        write!(f, "Mutant #{} [{}] [{}:{}-{}:{}] `{}` -> `{}`",
+               self.id, self.kind, start_l, start, end_l, end, before, self.after)
+    }
+}
+
+pub fn mutate(source: &str, file: &syn::File) -> Vec<Mutant> {
+    let mut visitor = MutationVisitor { source, mutants: Vec::new(), fn_depth: 0 };
+    visitor.visit_file(file);
+    visitor.mutants
+}
+
+struct MutationVisitor<'a> {
+    source: &'a str,
+    mutants: Vec<Mutant>,
+    // literals and blocks are only mutated inside function bodies
+    fn_depth: usize,
+}
+
+impl MutationVisitor<'_> {
+    fn add(&mut self, kind: MutantType, node: Span, after: String, edit: (Span, String)) {
+        let before = snippet(self.source, &node);
+        if before == after {
+            return;
+        }
+        self.mutants.push(Mutant { id: self.mutants.len() + 1, kind, span: node, before, after, edit });
+    }
+
+    fn in_fn<F>(&mut self, attrs: &[syn::Attribute], f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        // the test suite itself is never mutated
+        if attrs.iter().any(|attr| attr.path().is_ident("test")) || is_cfg_test(attrs) {
+            return;
+        }
+        self.fn_depth += 1;
+        f(self);
+        self.fn_depth -= 1;
+    }
+
+    fn binary(&mut self, i: &syn::ExprBinary) {
+        let Some((kind, replacements)) = binary_replacements(&i.op) else {
+            return;
+        };
+        let left = snippet(self.source, &span_of(&i.left));
+        let right = snippet(self.source, &span_of(&i.right));
+        for op in replacements {
+            self.add(kind, span_of(i), format!("{} {} {}", left, op, right), (span_of(&i.op), op.to_string()));
+        }
+    }
+
+    fn visit_block_removal(&mut self, block: &syn::Block) {
+        if self.fn_depth == 0 || block.stmts.is_empty() {
+            return;
+        }
+        self.add(MutantType::Block, span_of(block), "{}".to_string(), (span_of(block), "{}".to_string()));
+    }
+
+    fn cond(&mut self, cond: &Expr, replacements: &[&str]) {
+        if matches!(cond, Expr::Let(_) | Expr::Lit(_)) {
+            return;
+        }
+        for value in replacements {
+            self.add(MutantType::Cond, span_of(cond), value.to_string(), (span_of(cond), value.to_string()));
+        }
+    }
+}
+
+pub fn is_cfg_test(attrs: &[syn::Attribute]) -> bool {
+    attrs.iter().any(|attr| {
+        attr.path().is_ident("cfg") && attr.parse_args::<syn::Ident>().is_ok_and(|arg| arg == "test")
+    })
+}
+
+fn binary_replacements(op: &BinOp) -> Option<(MutantType, &'static [&'static str])> {
+    use MutantType::*;
+    let found: (MutantType, &'static [&'static str]) = match op {
+        BinOp::Add(_) => (Arith, &["-"]),
+        BinOp::Sub(_) => (Arith, &["+"]),
+        BinOp::Mul(_) => (Arith, &["/", "%"]),
+        BinOp::Div(_) => (Arith, &["*", "%"]),
+        BinOp::Rem(_) => (Arith, &["*", "/"]),
+        BinOp::Lt(_) => (Relational, &["<=", ">="]),
+        BinOp::Le(_) => (Relational, &["<", ">"]),
+        BinOp::Gt(_) => (Relational, &[">=", "<="]),
+        BinOp::Ge(_) => (Relational, &[">", "<"]),
+        BinOp::Eq(_) => (Relational, &["!="]),
+        BinOp::Ne(_) => (Relational, &["=="]),
+        BinOp::And(_) => (Logical, &["||"]),
+        BinOp::Or(_) => (Logical, &["&&"]),
+        BinOp::AddAssign(_) => (Assign, &["-="]),
+        BinOp::SubAssign(_) => (Assign, &["+="]),
+        BinOp::MulAssign(_) => (Assign, &["/="]),
+        BinOp::DivAssign(_) => (Assign, &["*="]),
+        BinOp::RemAssign(_) => (Assign, &["*="]),
+        BinOp::ShlAssign(_) => (Assign, &[">>="]),
+        BinOp::ShrAssign(_) => (Assign, &["<<="]),
+        BinOp::BitAndAssign(_) => (Assign, &["|="]),
+        BinOp::BitOrAssign(_) => (Assign, &["&="]),
+        _ => return None,
+    };
+    Some(found)
+}
+
+impl<'ast> Visit<'ast> for MutationVisitor<'_> {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        self.in_fn(&i.attrs, |v| {
+            // a body can only become `{}` when the function returns `()`
+            if matches!(i.sig.output, syn::ReturnType::Default) {
+                v.visit_block_removal(&i.block);
+            }
+            visit::visit_item_fn(v, i);
+        });
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        self.in_fn(&i.attrs, |v| {
+            if matches!(i.sig.output, syn::ReturnType::Default) {
+                v.visit_block_removal(&i.block);
+            }
+            visit::visit_impl_item_fn(v, i);
+        });
+    }
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if !is_cfg_test(&i.attrs) {
+            visit::visit_item_mod(self, i);
+        }
+    }
+
+    fn visit_expr_binary(&mut self, i: &'ast syn::ExprBinary) {
+        if self.fn_depth > 0 {
+            self.binary(i);
+        }
+        visit::visit_expr_binary(self, i);
+    }
+
+    fn visit_expr_unary(&mut self, i: &'ast syn::ExprUnary) {
+        if self.fn_depth > 0 && matches!(i.op, UnOp::Not(_) | UnOp::Neg(_)) {
+            let operand = snippet(self.source, &span_of(&i.expr));
+            self.add(MutantType::Unary, span_of(i), operand, (span_of(&i.op), String::new()));
+        }
+        visit::visit_expr_unary(self, i);
+    }
+
+    fn visit_expr_lit(&mut self, i: &'ast syn::ExprLit) {
+        if self.fn_depth > 0 {
+            let after = match &i.lit {
+                Lit::Bool(b) => Some((!b.value).to_string()),
+                Lit::Int(n) if n.base10_digits() == "0" => Some(format!("1{}", n.suffix())),
+                Lit::Int(n) => Some(format!("0{}", n.suffix())),
+                Lit::Str(s) if s.value().is_empty() => Some("\"__PLRG__\"".to_string()),
+                Lit::Str(_) => Some("\"\"".to_string()),
+                _ => None,
+            };
+            if let Some(after) = after {
+                self.add(MutantType::Literal, span_of(i), after.clone(), (span_of(i), after));
+            }
+        }
+        visit::visit_expr_lit(self, i);
+    }
+
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        if self.fn_depth > 0 {
+            self.cond(&i.cond, &["true", "false"]);
+        }
+        visit::visit_expr_if(self, i);
+    }
+
+    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+        if self.fn_depth > 0 {
+            self.cond(&i.cond, &["false"]);
+        }
+        visit::visit_expr_while(self, i);
+    }
+
+    fn visit_stmt(&mut self, s: &'ast syn::Stmt) {
+        // blocks used as statements (`if`, loops, `{ .. }`) evaluate to `()`
+        if let syn::Stmt::Expr(expr, _) = s {
+            match expr {
+                Expr::If(i) if i.else_branch.is_none() => self.visit_block_removal(&i.then_branch),
+                Expr::While(w) => self.visit_block_removal(&w.body),
+                Expr::ForLoop(f) => self.visit_block_removal(&f.body),
+                Expr::Block(b) => self.visit_block_removal(&b.block),
+                _ => {}
+            }
+        }
+        visit::visit_stmt(self, s);
+    }
+}
+
+// Byte offset of a (line, char column) position.
+pub fn offset(source: &str, line: usize, column: usize) -> usize {
+    let line_start: usize = source.split_inclusive('\n').take(line - 1).map(str::len).sum();
+    let line_text = &source[line_start..];
+    line_start + line_text.char_indices().nth(column).map_or(line_text.len(), |(idx, _)| idx)
+}
+
+// The source with the mutant's edit applied.
+pub fn apply(source: &str, mutant: &Mutant) -> String {
+    let ((start_l, start, end_l, end), replacement) = &mutant.edit;
+    let from = offset(source, *start_l, *start);
+    let to = offset(source, *end_l, *end);
+    format!("{}{}{}", &source[..from], replacement, &source[to..])
+}
+
+// A unified diff of the single hunk changed by the mutant.
+pub fn diff(path: &str, source: &str, mutant: &Mutant) -> String {
+    let mutated = apply(source, mutant);
+    let old: Vec<&str> = source.lines().collect();
+    let new: Vec<&str> = mutated.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..].iter().rev().zip(new[prefix..].iter().rev()).take_while(|(a, b)| a == b).count();
+    let old_hunk = &old[prefix..old.len() - suffix];
+    let new_hunk = &new[prefix..new.len() - suffix];
+
+    let mut text = format!("--- {}\n+++ {} (mutant #{})\n", path, path, mutant.id);
+    text += &format!("@@ -{},{} +{},{} @@\n", prefix + 1, old_hunk.len(), prefix + 1, new_hunk.len());
+    for line in old_hunk {
+        text += &format!("-{}\n", line);
+    }
+    for line in new_hunk {
+        text += &format!("+{}\n", line);
+    }
+    text
+}
@@ -0,0 +1,62 @@
+use crate::cfg::Cfg;
+use crate::changes::Changes;
+use crate::edge::{self, EdgeCoverage};
+use crate::hits::LineHits;
+use crate::summary;
+use crate::{snippet, Coverage};
+
+// Coverage of the code a diff adds or rewrites: the lines starting a function,
+// statement or decision, the statements overlapping a changed line, and the branch
+// edges of changed decisions. Returns the kinds of items whose coverage is
+// below `threshold` percent.
+pub fn report(file_name: &str, source: &str, coverage: &Coverage, cfgs: &[Cfg], hits: &LineHits, changes: &Changes,
+              threshold: Option<f64>) -> Vec<&'static str> {
+    let starts = summary::coverable_lines(coverage, cfgs);
+    let lines: Vec<usize> = changes.added.iter().copied().filter(|line| starts.contains(line)).collect();
+    let statements: Vec<_> = coverage.stmt_cov.values()
+        .filter(|span| (span.0..=span.2).any(|line| changes.added.contains(&line)))
+        .collect();
+    let edges: Vec<EdgeCoverage> = cfgs.iter().map(|cfg| EdgeCoverage::new(cfg, hits)).collect();
+    let branches: Vec<(&EdgeCoverage, usize)> = edges.iter()
+        .flat_map(|c| c.cfg.edges.iter().enumerate()
+            .filter(|(_, edge)| edge.cond.as_ref().is_some_and(|(span, _)| changes.added.contains(&span.0)))
+            .map(move |(e, _)| (c, e)))
+        .collect();
+
+    let covered_lines = lines.iter().filter(|line| hits.count(**line) > 0).count();
+    let covered_statements = statements.iter().filter(|span| hits.span_count(span) > 0).count();
+    let covered_branches = branches.iter().filter(|(c, e)| c.counts[*e] > 0).count();
+
+    println!("Patch coverage ({}, {} changed lines):", file_name, changes.added.len());
+    let mut failed = Vec::new();
+    for (kind, covered, total) in [("lines", covered_lines, lines.len()), ("statements", covered_statements, statements.len()),
+                                   ("branches", covered_branches, branches.len())] {
+        println!("- {}: {}/{} ({:.2}%)", kind, covered, total, percent(covered, total));
+        if threshold.is_some_and(|threshold| total > 0 && percent(covered, total) < threshold) {
+            failed.push(kind);
+        }
+    }
+
+    println!("Uncovered new code:");
+    for line in lines.iter().filter(|line| hits.count(**line) == 0) {
+        println!("- {}:{} line `{}`", file_name, line, source.lines().nth(line - 1).unwrap_or("").trim());
+    }
+    for span in statements.iter().filter(|span| hits.span_count(span) == 0) {
+        println!("- {}:{} statement `{}`", file_name, span.0, snippet(source, span));
+    }
+    for (c, e) in branches.iter().filter(|(c, e)| c.counts[*e] == 0) {
+        println!("- {}", edge::describe(file_name, c.cfg, &c.cfg.edges[*e]));
+    }
+    if let Some(threshold) = threshold {
+        if failed.is_empty() {
+            println!("Patch coverage meets {:.2}%", threshold);
+        } else {
+            println!("Patch coverage below {:.2}%: {}", threshold, failed.join(", "));
+        }
+    }
+    failed
+}
+
+fn percent(covered: usize, total: usize) -> f64 {
+    if total == 0 { 0.0 } else { covered as f64 / total as f64 * 100.0 }
+}
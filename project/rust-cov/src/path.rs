@@ -0,0 +1,285 @@
+use std::collections::{BTreeSet, VecDeque};
+use std::fs;
+use syn::visit::{self, Visit};
+
+use crate::cfg::{span_of, Cfg, ENTRY, EXIT};
+use crate::mutation::is_cfg_test;
+use crate::Span;
+
+// enumeration stops once this many paths are generated for a function
+const MAX_PATHS: usize = 10_000;
+
+pub type Path = Vec<usize>;
+
+fn successors(cfg: &Cfg, block: usize) -> BTreeSet<usize> {
+    cfg.edges.iter().filter(|e| e.from == block).map(|e| e.to).collect()
+}
+
+// Entry-to-exit paths in which no block occurs more than `loop_cap + 1` times,
+// i.e. every loop is iterated at most `loop_cap` extra times. The flag tells
+// whether enumeration stopped at `MAX_PATHS` with paths left.
+pub fn entry_exit_paths(cfg: &Cfg, loop_cap: usize) -> (Vec<Path>, bool) {
+    let mut paths = Vec::new();
+    let mut stack = vec![vec![ENTRY]];
+    while let Some(path) = stack.pop() {
+        if paths.len() >= MAX_PATHS {
+            return (paths, true);
+        }
+        let last = *path.last().unwrap();
+        if last == EXIT {
+            paths.push(path);
+            continue;
+        }
+        for next in successors(cfg, last).into_iter().rev() {
+            if path.iter().filter(|&&b| b == next).count() <= loop_cap {
+                let mut extended = path.clone();
+                extended.push(next);
+                stack.push(extended);
+            }
+        }
+    }
+    (paths, false)
+}
+
+// Simple paths (no repeated block, except a cycle closing on its first block)
+// that are not a proper subpath of any other simple path. The flag tells
+// whether generation stopped at `MAX_PATHS` with paths left to extend, so
+// some prime paths may be missing.
+pub fn prime_paths(cfg: &Cfg) -> (Vec<Path>, bool) {
+    let mut candidates = Vec::new();
+    let mut queue: VecDeque<Path> = cfg.blocks.iter().map(|b| vec![b.id]).collect();
+    let mut generated = queue.len();
+    let mut truncated = false;
+
+    while let Some(path) = queue.pop_front() {
+        let first = path[0];
+        let last = *path.last().unwrap();
+        let is_cycle = path.len() > 1 && first == last;
+        let mut extended = false;
+        if !is_cycle && generated >= MAX_PATHS && !successors(cfg, last).is_empty() {
+            truncated = true;
+        }
+        if !is_cycle && generated < MAX_PATHS {
+            for next in successors(cfg, last) {
+                if next == first || !path.contains(&next) {
+                    let mut longer = path.clone();
+                    longer.push(next);
+                    queue.push_back(longer);
+                    generated += 1;
+                    extended = true;
+                }
+            }
+        }
+        if !extended {
+            candidates.push(path);
+        }
+    }
+
+    let primes: Vec<Path> = candidates.iter()
+        .filter(|p| !candidates.iter().any(|q| q.len() > p.len() && is_subpath(p, q)))
+        .cloned()
+        .collect();
+    let mut unique = Vec::new();
+    for path in primes {
+        if !unique.contains(&path) {
+            unique.push(path);
+        }
+    }
+    (unique, truncated)
+}
+
+// Drops the last iteration of a loop until no block occurs more than
+// `loop_cap + 1` times, so an executed path compares with `entry_exit_paths`.
+pub fn cap_loops(path: &[usize], loop_cap: usize) -> Path {
+    let mut path = path.to_vec();
+    while let Some(&block) = path.iter().find(|&&b| path.iter().filter(|&&o| o == b).count() > loop_cap + 1) {
+        let occurrences: Vec<usize> = (0..path.len()).filter(|&i| path[i] == block).collect();
+        let (from, to) = (occurrences[occurrences.len() - 2], occurrences[occurrences.len() - 1]);
+        path.drain(from..to);
+    }
+    path
+}
+
+pub fn is_subpath(sub: &[usize], path: &[usize]) -> bool {
+    sub.is_empty() || path.windows(sub.len()).any(|w| w == sub)
+}
+
+// Execution traces: one run per row, an optional `name:` prefix followed by the
+// executed source lines in order, e.g. `tests::test_abs: 1 2 3 6`. Other tokens
+// on the row (such as decision evaluations) are skipped.
+pub fn load_traces(path: &str) -> Vec<(String, Vec<usize>)> {
+    load_rows(path).into_iter()
+        .map(|(name, tokens)| (name, tokens.iter().filter_map(|t| t.parse().ok()).collect()))
+        .collect()
+}
+
+// The named rows of a traces file with all of their tokens.
+pub fn load_rows(path: &str) -> Vec<(String, Vec<String>)> {
+    let contents = fs::read_to_string(path).expect("Something went wrong reading the traces file");
+    contents.lines()
+        .enumerate()
+        .filter(|(_, row)| !row.trim().is_empty())
+        .map(|(idx, row)| {
+            let (name, tokens) = match row.rsplit_once(':') {
+                Some((name, tokens)) => (name.trim().to_string(), tokens),
+                None => (format!("run {}", idx + 1), row),
+            };
+            let tokens = tokens.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+                .collect();
+            (name, tokens)
+        })
+        .collect()
+}
+
+// Maps a line trace onto block paths of `cfg`, one path per invocation (an
+// invocation starts at every occurrence of the signature line).
+pub fn trace_paths(cfg: &Cfg, trace: &[usize]) -> Vec<Path> {
+    let span_index = |block: usize, line: usize| cfg.blocks[block].spans.iter().position(|span| span.0 == line);
+
+    let mut paths = Vec::new();
+    let mut path = vec![ENTRY];
+    // index of the last span reached in the current block
+    let mut pos = 0;
+    let mut invoked = false;
+    for &line in trace {
+        if !cfg.blocks.iter().any(|b| span_index(b.id, line).is_some()) {
+            continue;
+        }
+        invoked = true;
+        if line == cfg.span.0 {
+            if path.len() > 1 {
+                paths.push(finish(cfg, path));
+                path = vec![ENTRY];
+                pos = 0;
+            }
+            continue;
+        }
+
+        let cur = *path.last().unwrap();
+        if span_index(cur, line).is_some_and(|idx| idx > pos) {
+            pos = span_index(cur, line).unwrap();
+            continue;
+        }
+        if let Some(hops) = route(cfg, cur, |b| span_index(b, line).is_some()) {
+            pos = span_index(*hops.last().unwrap(), line).unwrap();
+            path.extend(hops);
+        }
+    }
+    if invoked {
+        paths.push(finish(cfg, path));
+    }
+    paths
+}
+
+fn finish(cfg: &Cfg, mut path: Path) -> Path {
+    let cur = *path.last().unwrap();
+    if cur != EXIT {
+        if let Some(hops) = route(cfg, cur, |b| b == EXIT) {
+            path.extend(hops);
+        }
+    }
+    path
+}
+
+// Shortest hop sequence from `from` to a block accepted by `target`, passing
+// only through blocks without code of their own (those never show in a trace).
+fn route(cfg: &Cfg, from: usize, target: impl Fn(usize) -> bool) -> Option<Path> {
+    let mut queue = VecDeque::from([(from, Vec::new())]);
+    let mut seen = BTreeSet::from([from]);
+    while let Some((block, hops)) = queue.pop_front() {
+        for next in successors(cfg, block) {
+            let mut hops = hops.clone();
+            hops.push(next);
+            if target(next) {
+                return Some(hops);
+            }
+            if cfg.blocks[next].spans.is_empty() && seen.insert(next) {
+                queue.push_back((next, hops));
+            }
+        }
+    }
+    None
+}
+
+pub fn render(cfg: &Cfg, path: &[usize]) -> String {
+    path.iter()
+        .map(|&b| match b {
+            ENTRY => "entry".to_string(),
+            EXIT => "exit".to_string(),
+            b => cfg.blocks[b].spans.first().map_or(format!("bb{}", b), |span| span.0.to_string()),
+        })
+        .collect::<Vec<_>>()
+        .join(" → ")
+}
+
+// The functions of the file leaving out the tests, as mutation leaves them out.
+pub fn without_tests(file: &syn::File, cfgs: Vec<Cfg>) -> Vec<Cfg> {
+    let mut tests = TestSpans(Vec::new());
+    tests.visit_file(file);
+    cfgs.into_iter()
+        .filter(|cfg| !tests.0.iter().any(|test| test.0 <= cfg.span.0 && cfg.span.2 <= test.2))
+        .collect()
+}
+
+// The spans of `#[test]` functions and `#[cfg(test)]` modules and functions.
+struct TestSpans(Vec<Span>);
+
+impl<'ast> Visit<'ast> for TestSpans {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        if i.attrs.iter().any(|attr| attr.path().is_ident("test")) || is_cfg_test(&i.attrs) {
+            self.0.push(span_of(i));
+        }
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        if i.attrs.iter().any(|attr| attr.path().is_ident("test")) || is_cfg_test(&i.attrs) {
+            self.0.push(span_of(i));
+        }
+        visit::visit_impl_item_fn(self, i);
+    }
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if is_cfg_test(&i.attrs) {
+            self.0.push(span_of(i));
+        }
+        visit::visit_item_mod(self, i);
+    }
+}
+
+pub fn report(cfgs: &[Cfg], traces: &[(String, Vec<usize>)], loop_cap: usize) {
+    println!("Path coverage (loop cap {}):", loop_cap);
+    for cfg in cfgs {
+        let executed: Vec<(String, Path)> = traces.iter()
+            .flat_map(|(name, trace)| trace_paths(cfg, trace).into_iter().map(move |p| (name.clone(), p)))
+            .collect();
+
+        let (paths, paths_truncated) = entry_exit_paths(cfg, loop_cap);
+        let (primes, primes_truncated) = prime_paths(cfg);
+        let path_hit = |p: &Path| executed.iter().filter(|(_, e)| cap_loops(e, loop_cap) == *p).map(|(n, _)| n.clone()).collect::<BTreeSet<_>>();
+        let prime_hit = |p: &Path| executed.iter().filter(|(_, e)| is_subpath(p, e)).map(|(n, _)| n.clone()).collect::<BTreeSet<_>>();
+
+        println!("- {}:", cfg.name);
+        for (kind, list, truncated, hit) in [("path", &paths, paths_truncated, &path_hit as &dyn Fn(&Path) -> BTreeSet<String>),
+                                             ("prime path", &primes, primes_truncated, &prime_hit)] {
+            let covered = list.iter().filter(|p| !hit(p).is_empty()).count();
+            if truncated {
+                // the total is unknown, so there is no ratio to give
+                println!("  - {}: {}/{}+ (stopped at the cap of {} generated paths, the list below is incomplete)", kind, covered, list.len(), MAX_PATHS);
+            } else {
+                let ratio = if list.is_empty() { 0.0 } else { covered as f64 / list.len() as f64 * 100.0 };
+                println!("  - {}: {}/{} ({:.2}%)", kind, covered, list.len(), ratio);
+            }
+            for p in list {
+                let by = hit(p);
+                let marker = if by.is_empty() { '-' } else { '*' };
+                let tests = if by.is_empty() { String::new() } else {
+                    format!(" ({})", by.into_iter().collect::<Vec<_>>().join(", "))
+                };
+                println!("    {} {}{}", marker, render(cfg, p), tests);
+            }
+        }
+    }
+}
@@ -0,0 +1,258 @@
+use std::fs;
+use syn::visit::{self, Visit};
+use syn::{Expr, Stmt};
+
+use crate::cfg::span_of;
+use crate::mutation::{is_cfg_test, offset};
+use crate::runner::{Sandbox, Status};
+
+// Environment variable naming the file a test run appends its trace to.
+pub const TRACE_VAR: &str = "RUST_COV_TRACE";
+
+// `__rust_cov_probe!` appends the line to the trace file and
+// `__rust_cov_decision!` one evaluation of a decision as `@line.col=TF/T`
+// (condition values, `-` for short-circuited ones, then the outcome). Both are
+// no-ops when no trace is requested.
+const PROBE_MACROS: &str = "#[allow(unused_macros)]
+macro_rules! __rust_cov_record {
+    ($text:expr) => {{
+        static TRACE: ::std::sync::OnceLock<Option<::std::sync::Mutex<::std::fs::File>>> = ::std::sync::OnceLock::new();
+        let trace = TRACE.get_or_init(|| {
+            let path = ::std::env::var_os(\"VAR\")?;
+            ::std::fs::OpenOptions::new().create(true).append(true).open(path).ok().map(::std::sync::Mutex::new)
+        });
+        if let Some(file) = trace {
+            if let Ok(mut file) = file.lock() {
+                let _ = ::std::io::Write::write_all(&mut *file, $text.as_bytes());
+            }
+        }
+    }};
+}
+#[allow(unused_macros)]
+macro_rules! __rust_cov_probe {
+    ($line:expr) => { __rust_cov_record!(concat!($line, \" \")) };
+}
+#[allow(unused_macros)]
+macro_rules! __rust_cov_decision {
+    ($line:expr, $col:expr, $values:expr, $outcome:expr) => {
+        __rust_cov_record!(format!(\"@{}.{}={}/{} \", $line, $col, ::std::string::String::from_utf8_lossy(&$values),
+                                   if $outcome { 'T' } else { 'F' }))
+    };
+}
+";
+
+// Rewrites `source` so that running it records the executed lines in the
+// order `path::trace_paths` expects: the signature line on every call, then
+// the first line of every statement reached. Loop heads record their line on
+// every iteration; arms and `else if` conditions record theirs as well. The
+// conditions of `if` and `while` also record every evaluation for MC/DC.
+pub fn instrument(source: &str, file: &syn::File) -> String {
+    let mut probes = Probes { source, inserts: Vec::new(), fn_depth: 0, else_if: false };
+    probes.visit_file(file);
+    if probes.inserts.is_empty() {
+        return source.to_string();
+    }
+
+    let at = file.attrs.iter()
+        .filter(|attr| matches!(attr.style, syn::AttrStyle::Inner(_)))
+        .map(|attr| { let span = span_of(attr); offset(source, span.2, span.3) })
+        .max()
+        .map_or(0, |end| end + source[end..].find('\n').map_or(source.len() - end, |nl| nl + 1));
+    probes.inserts.push((at, PROBE_MACROS.replace("VAR", TRACE_VAR)));
+
+    // stable sort: text inserted at the same offset keeps its visiting order
+    probes.inserts.sort_by_key(|(at, _)| *at);
+    let mut text = String::new();
+    let mut pos = 0;
+    for (at, insert) in &probes.inserts {
+        text += &source[pos..*at];
+        text += insert;
+        pos = *at;
+    }
+    text += &source[pos..];
+    text
+}
+
+fn probe(line: usize) -> String {
+    format!("__rust_cov_probe!({}); ", line)
+}
+
+struct Probes<'a> {
+    source: &'a str,
+    // (byte offset, text inserted there)
+    inserts: Vec<(usize, String)>,
+    // probes only go into function bodies
+    fn_depth: usize,
+    // whether the `if` visited next is the `else if` of another one
+    else_if: bool,
+}
+
+impl Probes<'_> {
+    fn at_start(&self, span: &crate::Span) -> usize {
+        offset(self.source, span.0, span.1)
+    }
+
+    fn at_end(&self, span: &crate::Span) -> usize {
+        offset(self.source, span.2, span.3)
+    }
+
+    // `{ probe; <expr> }` in place of the expression.
+    fn wrap(&mut self, expr: &Expr) {
+        let span = span_of(expr);
+        self.inserts.push((self.at_start(&span), format!("{{ {}", probe(span.0))));
+        self.inserts.push((self.at_end(&span), " }".to_string()));
+    }
+
+    // Records the values of the conditions of a decision and its outcome,
+    // optionally preceded by a line probe:
+    // `{ probe; let mut v = *b"--"; let r = <cond with each condition
+    // recording into v>; record(v, r); r }`.
+    fn decision(&mut self, cond: &Expr, line_probe: bool) {
+        if !self.in_body() || has_let(cond) {
+            if line_probe && self.in_body() && !matches!(cond, Expr::Let(_)) {
+                self.wrap(cond);
+            }
+            return;
+        }
+        let span = span_of(cond);
+        let conditions = conditions(cond);
+        let probe_text = if line_probe { probe(span.0) } else { String::new() };
+        self.inserts.push((self.at_start(&span), format!("{{ {}let mut __rust_cov_v = *b\"{}\"; let __rust_cov_r = ",
+                                                         probe_text, "-".repeat(conditions.len()))));
+        for (idx, condition) in conditions.iter().enumerate() {
+            let c = span_of(*condition);
+            self.inserts.push((self.at_start(&c), "{ let __rust_cov_c = (".to_string()));
+            self.inserts.push((self.at_end(&c), format!(
+                "); __rust_cov_v[{}] = if __rust_cov_c {{ b'T' }} else {{ b'F' }}; __rust_cov_c }}", idx)));
+        }
+        self.inserts.push((self.at_end(&span), format!(
+            "; __rust_cov_decision!({}, {}, __rust_cov_v, __rust_cov_r); __rust_cov_r }}", span.0, span.1)));
+    }
+
+    fn in_body(&self) -> bool {
+        self.fn_depth > 0
+    }
+
+    // A probe right after the opening brace of a block.
+    fn enter(&mut self, block: &syn::Block, line: usize) {
+        let at = self.at_start(&span_of(block)) + 1;
+        self.inserts.push((at, probe(line)));
+    }
+
+    fn in_fn<F>(&mut self, attrs: &[syn::Attribute], sig: &syn::Signature, block: &syn::Block, f: F)
+    where
+        F: FnOnce(&mut Self),
+    {
+        // tests are not traced, and a `const fn` cannot do I/O
+        if attrs.iter().any(|attr| attr.path().is_ident("test")) || sig.constness.is_some() {
+            return;
+        }
+        self.enter(block, span_of(sig).0);
+        self.fn_depth += 1;
+        f(self);
+        self.fn_depth -= 1;
+    }
+}
+
+impl<'ast> Visit<'ast> for Probes<'_> {
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        self.in_fn(&i.attrs, &i.sig, &i.block, |p| visit::visit_item_fn(p, i));
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        self.in_fn(&i.attrs, &i.sig, &i.block, |p| visit::visit_impl_item_fn(p, i));
+    }
+
+    fn visit_item_mod(&mut self, i: &'ast syn::ItemMod) {
+        if !is_cfg_test(&i.attrs) {
+            visit::visit_item_mod(self, i);
+        }
+    }
+
+    fn visit_stmt(&mut self, s: &'ast Stmt) {
+        // a `while` records its line through the probe in its condition
+        let probed_cond = matches!(s, Stmt::Expr(Expr::While(w), _) if !matches!(*w.cond, Expr::Let(_)));
+        if self.fn_depth > 0 && !matches!(s, Stmt::Item(_)) && !probed_cond {
+            let span = span_of(s);
+            self.inserts.push((self.at_start(&span), probe(span.0)));
+        }
+        visit::visit_stmt(self, s);
+    }
+
+    fn visit_expr_while(&mut self, i: &'ast syn::ExprWhile) {
+        self.decision(&i.cond, true);
+        visit::visit_expr_while(self, i);
+    }
+
+    fn visit_expr_for_loop(&mut self, i: &'ast syn::ExprForLoop) {
+        if self.fn_depth > 0 {
+            self.enter(&i.body, span_of(&i.pat).0);
+        }
+        visit::visit_expr_for_loop(self, i);
+    }
+
+    fn visit_expr_if(&mut self, i: &'ast syn::ExprIf) {
+        // the condition of an `else if` is not a statement of its own
+        let is_else_if = std::mem::take(&mut self.else_if);
+        self.decision(&i.cond, is_else_if);
+        let else_if = matches!(&i.else_branch, Some((_, e)) if matches!(**e, Expr::If(_)));
+        self.visit_expr(&i.cond);
+        self.visit_block(&i.then_branch);
+        if let Some((_, else_branch)) = &i.else_branch {
+            self.else_if = else_if;
+            self.visit_expr(else_branch);
+            self.else_if = false;
+        }
+    }
+
+    fn visit_arm(&mut self, arm: &'ast syn::Arm) {
+        if self.fn_depth > 0 && !matches!(*arm.body, Expr::Block(_)) {
+            self.wrap(&arm.body);
+        }
+        visit::visit_arm(self, arm);
+    }
+}
+
+// The conditions of a decision: the operands of its `&&` / `||` chains.
+pub fn conditions(cond: &Expr) -> Vec<&Expr> {
+    match cond {
+        Expr::Binary(b) if matches!(b.op, syn::BinOp::And(_) | syn::BinOp::Or(_)) => {
+            let mut all = conditions(&b.left);
+            all.extend(conditions(&b.right));
+            all
+        }
+        Expr::Paren(p) if matches!(*p.expr, Expr::Binary(ref b) if matches!(b.op, syn::BinOp::And(_) | syn::BinOp::Or(_))) => conditions(&p.expr),
+        _ => vec![cond],
+    }
+}
+
+fn has_let(cond: &Expr) -> bool {
+    match cond {
+        Expr::Let(_) => true,
+        Expr::Binary(b) => has_let(&b.left) || has_let(&b.right),
+        Expr::Paren(p) => has_let(&p.expr),
+        _ => false,
+    }
+}
+
+// Runs every test of the crate on its own against the instrumented file and
+// returns the recorded tokens per test: executed lines, as read by
+// `path::load_traces`, and decision evaluations, as read by `mcdc::load`.
+pub fn collect(sandbox: &Sandbox) -> Vec<(String, Vec<String>, Status)> {
+    let syntax = syn::parse_file(sandbox.source()).expect("Unable to parse file");
+    assert!(sandbox.build(&instrument(sandbox.source(), &syntax)), "The instrumented crate does not build");
+
+    let trace_file = std::env::temp_dir().join("rust-cov-trace.txt");
+    let mut traces = Vec::new();
+    for name in sandbox.list_tests() {
+        fs::remove_file(&trace_file).ok();
+        let status = sandbox.run_test(&name, (TRACE_VAR, trace_file.to_string_lossy().into_owned()));
+        let tokens = fs::read_to_string(&trace_file).unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_string)
+            .collect();
+        traces.push((name, tokens, status));
+    }
+    sandbox.restore();
+    traces
+}
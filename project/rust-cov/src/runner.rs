@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::hits::LineHits;
+use crate::mutation::{self, Mutant, MutantType, ALL_MUTANT_TYPES};
+use crate::schemata;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Killed,
+    Survived,
+    Timeout,
+    CompileError,
+    NotCovered,
+    Equivalent,
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Status::Killed => "KILLED",
+            Status::Survived => "ALIVE",
+            Status::Timeout => "TIMEOUT",
+            Status::CompileError => "COMPILE ERROR",
+            Status::NotCovered => "NO COVERAGE",
+            Status::Equivalent => "EQUIVALENT",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MutantResult {
+    pub mutant: Mutant,
+    pub status: Status,
+    pub millis: u128,
+    // why a mutant was not run, e.g. the reason it is equivalent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+}
+
+// Everything needed to resume a run: the mutated file and the mutants done so far.
+#[derive(Serialize, Deserialize, Default)]
+pub struct Results {
+    pub file: String,
+    pub results: Vec<MutantResult>,
+}
+
+impl Results {
+    pub fn load(path: &str) -> Option<Self> {
+        let contents = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    pub fn save(&self, path: &str) {
+        let json = serde_json::to_string_pretty(self).expect("Something went wrong serializing the results");
+        fs::write(path, json).expect("Something went wrong writing the results file");
+    }
+
+    // A stored result is reused only when the mutant itself is unchanged.
+    fn find(&self, mutant: &Mutant) -> Option<&MutantResult> {
+        self.results.iter().find(|r| r.mutant.id == mutant.id && r.mutant.edit == mutant.edit)
+    }
+}
+
+// Counts like js-mutest's `MutationScore`: a timeout counts as killed, a
+// mutant no test reaches as alive, and mutants that do not compile or are
+// equivalent are left out of the total.
+pub struct MutationScore {
+    counters: Vec<(MutantType, usize, usize)>,
+    pub killed: usize,
+    pub total: usize,
+}
+
+impl MutationScore {
+    pub fn new(results: &[MutantResult]) -> Self {
+        let mut score = MutationScore { counters: Vec::new(), killed: 0, total: 0 };
+        for kind in ALL_MUTANT_TYPES {
+            let of_kind = results.iter().filter(|r| r.mutant.kind == kind && !matches!(r.status, Status::CompileError | Status::Equivalent));
+            let (killed, total) = of_kind.fold((0, 0), |(k, t), r| (k + matches!(r.status, Status::Killed | Status::Timeout) as usize, t + 1));
+            score.killed += killed;
+            score.total += total;
+            score.counters.push((kind, killed, total));
+        }
+        score
+    }
+}
+
+impl fmt::Display for MutationScore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let ratio = if self.total == 0 { 0.0 } else { self.killed as f64 / self.total as f64 * 100.0 };
+        write!(f, "{} / {} ({:.2}%)", self.killed, self.total, ratio)?;
+        for (kind, killed, total) in &self.counters {
+            if *total > 0 {
+                write!(f, "\n  [{:<10}]: {:>3} / {:>3}", kind.to_string(), killed, total)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Which tests to run for a mutant.
+pub enum Selection {
+    All,
+    NotCovered,
+    Tests(Vec<String>),
+}
+
+// Coverage of the mutated file: line hits of the whole suite and/or per-test
+// traces (named by test path, e.g. `tests::test_abs: 1 2 5`).
+#[derive(Default)]
+pub struct MutantCoverage {
+    pub hits: Option<LineHits>,
+    pub traces: Vec<(String, Vec<usize>)>,
+}
+
+impl MutantCoverage {
+    pub fn select(&self, mutant: &Mutant) -> Selection {
+        let lines = mutant_lines(mutant);
+        if self.hits.as_ref().is_some_and(|hits| lines.iter().all(|&l| hits.count(l) == 0)) {
+            return Selection::NotCovered;
+        }
+        if self.traces.is_empty() {
+            return Selection::All;
+        }
+        let tests: Vec<String> = self.traces.iter()
+            .filter(|(_, trace)| trace.iter().any(|l| lines.contains(l)))
+            .map(|(name, _)| name.clone())
+            .collect();
+        if tests.is_empty() { Selection::NotCovered } else { Selection::Tests(tests) }
+    }
+}
+
+// Lines whose execution reaches the mutant: the first line of an expression,
+// or the lines inside a removed block (its opening line runs either way).
+fn mutant_lines(mutant: &Mutant) -> Vec<usize> {
+    let (start, _, end, _) = mutant.span;
+    if mutant.kind == MutantType::Block && end > start + 1 {
+        (start + 1..end).collect()
+    } else {
+        vec![start]
+    }
+}
+
+// A copy of the crate under test in which one file is rewritten per mutant,
+// in a directory of its own that is removed when the sandbox is dropped.
+pub struct Sandbox {
+    dir: PathBuf,
+    file: PathBuf,
+    original: String,
+    timeout: Duration,
+}
+
+impl Sandbox {
+    // `file` is relative to `crate_dir`.
+    pub fn new(crate_dir: &str, file: &str, timeout: Duration) -> Self {
+        let name = Path::new(crate_dir).canonicalize().expect("Something went wrong locating the crate")
+            .file_name().map_or("crate".to_string(), |n| n.to_string_lossy().into_owned());
+        let dir = unique_dir(&name);
+        copy_dir(Path::new(crate_dir), &dir);
+        let original = fs::read_to_string(dir.join(file)).expect("Something went wrong reading the mutated file");
+        Sandbox { file: dir.join(file), dir, original, timeout }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.original
+    }
+
+    // Runs the test suite on the original source; a failing suite makes every
+    // mutant look killed, so the run stops there.
+    pub fn check_baseline(&self) {
+        let (status, _) = self.cargo(&["test", "--no-run"], None, Duration::MAX);
+        assert!(status == Status::Survived, "The crate does not build ({})", status);
+        let (status, _) = self.cargo(&["test"], None, self.timeout);
+        assert!(status == Status::Survived, "The test suite does not pass on the original source ({})", status);
+    }
+
+    // `tests` restricts the run to these tests (all of them when empty).
+    pub fn run(&self, mutant: &Mutant, tests: &[String]) -> MutantResult {
+        fs::write(&self.file, mutation::apply(&self.original, mutant)).expect("Something went wrong writing the mutant");
+        let (status, elapsed) = self.cargo(&test_args(tests), None, self.timeout);
+        self.restore();
+        MutantResult { mutant: mutant.clone(), status, millis: elapsed.as_millis(), note: None }
+    }
+
+    // Builds the tests once with `source` in place of the file (e.g. the
+    // schemata); false when it does not compile.
+    pub fn build(&self, source: &str) -> bool {
+        fs::write(&self.file, source).expect("Something went wrong writing the rewritten source");
+        let (status, _) = self.cargo(&["test", "--no-run"], None, Duration::MAX);
+        status == Status::Survived
+    }
+
+    // Runs a mutant compiled in by `build`, switched on at runtime.
+    pub fn run_switched(&self, mutant: &Mutant, tests: &[String]) -> MutantResult {
+        let (status, elapsed) = self.cargo(&test_args(tests), Some((schemata::MUTANT_VAR, mutant.id.to_string())), self.timeout);
+        MutantResult { mutant: mutant.clone(), status, millis: elapsed.as_millis(), note: None }
+    }
+
+    pub fn restore(&self) {
+        fs::write(&self.file, &self.original).expect("Something went wrong restoring the original source");
+    }
+
+    // `Survived` here means the command succeeded.
+    // Names of all tests of the crate, as accepted by `--exact`.
+    pub fn list_tests(&self) -> Vec<String> {
+        let output = Command::new("cargo").args(["test", "--quiet", "--", "--list"])
+            .current_dir(&self.dir)
+            .stderr(Stdio::null())
+            .output()
+            .expect("Something went wrong listing the tests");
+        String::from_utf8_lossy(&output.stdout).lines()
+            .filter_map(|line| line.strip_suffix(": test"))
+            .map(str::to_string)
+            .collect()
+    }
+
+    // Runs a single test with an extra environment variable.
+    pub fn run_test(&self, name: &str, env: (&str, String)) -> Status {
+        self.cargo(&test_args(&[name.to_string()]), Some(env), self.timeout).0
+    }
+
+    fn cargo<S: AsRef<std::ffi::OsStr>>(&self, args: &[S], env: Option<(&str, String)>, timeout: Duration) -> (Status, Duration) {
+        let start = Instant::now();
+        let mut command = Command::new("cargo");
+        command.arg(&args[0]).arg("--quiet").args(&args[1..])
+            .current_dir(&self.dir)
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped());
+        if let Some((var, value)) = env {
+            command.env(var, value);
+        }
+        #[cfg(unix)]
+        std::os::unix::process::CommandExt::process_group(&mut command, 0);
+        let mut child = command.spawn().expect("Something went wrong running cargo");
+        // drain stderr on its own thread so a chatty build cannot block the child
+        let mut stderr = child.stderr.take().unwrap();
+        let reader = thread::spawn(move || {
+            let mut text = String::new();
+            std::io::Read::read_to_string(&mut stderr, &mut text).ok();
+            text
+        });
+
+        loop {
+            if let Some(exit) = child.try_wait().expect("Something went wrong waiting for cargo") {
+                let stderr = reader.join().unwrap_or_default();
+                let status = if exit.success() {
+                    Status::Survived
+                } else if stderr.contains("could not compile") {
+                    Status::CompileError
+                } else {
+                    Status::Killed
+                };
+                return (status, start.elapsed());
+            }
+            if start.elapsed() > timeout {
+                kill(&mut child);
+                return (Status::Timeout, start.elapsed());
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        fs::remove_dir_all(&self.dir).ok();
+    }
+}
+
+// A new directory under the system's temp dir, named after the process so
+// concurrent runs never share one.
+fn unique_dir(name: &str) -> PathBuf {
+    for n in 0.. {
+        let dir = std::env::temp_dir().join(format!("rust-cov-mutest-{}-{}-{}", name, std::process::id(), n));
+        // fails when the directory exists, e.g. left over by a killed run
+        if fs::create_dir(&dir).is_ok() {
+            return dir;
+        }
+    }
+    unreachable!()
+}
+
+fn test_args(tests: &[String]) -> Vec<String> {
+    let mut args = vec!["test".to_string()];
+    if !tests.is_empty() {
+        args.extend(["--".to_string(), "--exact".to_string()]);
+        args.extend(tests.iter().cloned());
+    }
+    args
+}
+
+// Kills cargo together with the test binary it spawned.
+fn kill(child: &mut std::process::Child) {
+    #[cfg(unix)]
+    Command::new("kill").arg("-KILL").arg(format!("-{}", child.id())).status().ok();
+    child.kill().ok();
+    child.wait().ok();
+}
+
+// Copies the crate, leaving out build output and version control.
+fn copy_dir(from: &Path, to: &Path) {
+    fs::create_dir_all(to).expect("Something went wrong creating the sandbox");
+    for entry in fs::read_dir(from).expect("Something went wrong reading the crate") {
+        let entry = entry.expect("Something went wrong reading the crate");
+        let name = entry.file_name();
+        if name == "target" || name == ".git" {
+            continue;
+        }
+        let target = to.join(&name);
+        if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            copy_dir(&entry.path(), &target);
+        } else {
+            fs::copy(entry.path(), &target).expect("Something went wrong copying the crate");
+        }
+    }
+}
+
+// Runs every mutant not already in `out`, saving after each one so an
+// interrupted run picks up where it stopped. With `schemata`, the mutants are
+// compiled into one build; those it cannot hold get a build of their own.
+// Mutants `coverage` shows no test reaches, and those in `equivalent`, are not
+// run at all.
+pub fn execute(sandbox: &Sandbox, file: &str, mutants: &[Mutant], out: &str, schemata: bool,
+               coverage: &MutantCoverage, equivalent: &BTreeMap<usize, String>) -> Results {
+    let stored = Results::load(out).filter(|r| r.file == file).unwrap_or_default();
+    let mut results = Results { file: file.to_string(), results: Vec::new() };
+    let pending: Vec<&Mutant> = mutants.iter()
+        .filter(|m| stored.find(m).is_none() && !equivalent.contains_key(&m.id))
+        .filter(|m| !matches!(coverage.select(m), Selection::NotCovered))
+        .collect();
+    if !pending.is_empty() {
+        sandbox.check_baseline();
+    }
+
+    let mut switched = BTreeSet::new();
+    if schemata && !pending.is_empty() {
+        let syntax = syn::parse_file(sandbox.source()).expect("Unable to parse file");
+        let (source, ids) = schemata::generate(sandbox.source(), &syntax, &pending);
+        if sandbox.build(&source) {
+            println!("Schemata: {} of {} mutants in one build", ids.len(), pending.len());
+            switched = ids;
+        } else {
+            println!("Schemata: the build failed, compiling each mutant on its own");
+            sandbox.restore();
+        }
+    }
+
+    // switched mutants first, while the schemata build is in place
+    let (first, rest): (Vec<&Mutant>, Vec<&Mutant>) = mutants.iter().partition(|m| switched.contains(&m.id));
+    for mutant in first.into_iter().chain(rest) {
+        if let Some(result) = stored.find(mutant) {
+            results.results.push(result.clone());
+            continue;
+        }
+        if let Some(reason) = equivalent.get(&mutant.id) {
+            let result = MutantResult { mutant: mutant.clone(), status: Status::Equivalent, millis: 0, note: Some(reason.clone()) };
+            results.results.push(result);
+            continue;
+        }
+        let tests = match coverage.select(mutant) {
+            Selection::NotCovered => None,
+            Selection::All => Some(Vec::new()),
+            Selection::Tests(tests) => Some(tests),
+        };
+        if !switched.is_empty() && !switched.contains(&mutant.id) {
+            sandbox.restore();
+            switched.clear();
+        }
+        let result = match tests {
+            None => MutantResult { mutant: mutant.clone(), status: Status::NotCovered, millis: 0, note: None },
+            Some(tests) if switched.contains(&mutant.id) => sandbox.run_switched(mutant, &tests),
+            Some(tests) => sandbox.run(mutant, &tests),
+        };
+        println!("[{}] {}", result.status, mutant);
+        results.results.push(result);
+        results.save(out);
+    }
+    sandbox.restore();
+    results.results.sort_by_key(|r| r.mutant.id);
+    results.save(out);
+    results
+}
+
+pub fn summary(results: &Results) -> String {
+    let mut text = format!("Mutation testing: {}\n", results.file);
+    for status in [Status::Survived, Status::NotCovered, Status::Timeout, Status::CompileError, Status::Equivalent] {
+        let listed: Vec<&MutantResult> = results.results.iter().filter(|r| r.status == status).collect();
+        if !listed.is_empty() {
+            text += &format!("- {} ({}):\n", status, listed.len());
+            for result in listed {
+                match &result.note {
+                    Some(note) => text += &format!("  {} ({})\n", result.mutant, note),
+                    None => text += &format!("  {}\n", result.mutant),
+                }
+            }
+        }
+    }
+    text += &format!("Mutation score: {}\n", MutationScore::new(&results.results));
+    text
+}
@@ -0,0 +1,163 @@
+use serde::Serialize;
+use std::path::Path;
+
+use crate::runner::{Results, Status};
+use crate::summary::FileCoverage;
+use crate::Span;
+
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+// (id, description, level), indexed by `ruleIndex`
+const RULES: [(&str, &str, &str); 3] = [
+    ("uncovered-function", "Function never called by the tests", "warning"),
+    ("uncovered-branch", "Branch never taken by the tests", "warning"),
+    ("surviving-mutant", "Mutant not killed by any test", "error"),
+];
+
+#[derive(Serialize)]
+pub struct Log {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<Run>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    // spans count characters
+    column_kind: &'static str,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Serialize)]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    rules: Vec<Rule>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Rule {
+    id: &'static str,
+    short_description: Message,
+    default_configuration: Configuration,
+}
+
+#[derive(Serialize)]
+struct Configuration {
+    level: &'static str,
+}
+
+#[derive(Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    rule_id: &'static str,
+    rule_index: usize,
+    level: &'static str,
+    message: Message,
+    locations: Vec<Location>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    region: Region,
+}
+
+#[derive(Serialize)]
+struct ArtifactLocation {
+    uri: String,
+}
+
+// SARIF lines and columns count from 1, the end column is exclusive.
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+fn result(rule_index: usize, text: String, uri: &str, span: &Span) -> SarifResult {
+    let (start_line, start, end_line, end) = *span;
+    let region = Region { start_line, start_column: start + 1, end_line, end_column: end + 1 };
+    SarifResult {
+        rule_id: RULES[rule_index].0,
+        rule_index,
+        level: RULES[rule_index].2,
+        message: Message { text },
+        locations: vec![Location {
+            physical_location: PhysicalLocation { artifact_location: ArtifactLocation { uri: uri.to_string() }, region },
+        }],
+    }
+}
+
+// A SARIF 2.1 log with a result for every function never called, every branch
+// arm never taken in a function that was called, and every mutant left alive
+// by `rust-cov mutest`.
+pub fn log(files: &[FileCoverage], mutants: Option<&Results>) -> Log {
+    let mut results = Vec::new();
+    for file in files {
+        for function in file.functions.iter().filter(|f| f.hits == 0) {
+            results.push(result(0, format!("Function `{}` is never called", function.name), &file.path, &function.span));
+        }
+        for branch in &file.branches {
+            // decisions in functions never called are reported with their function
+            let called = file.functions.iter()
+                .filter(|f| (f.span.0..=f.span.2).contains(&branch.line))
+                .min_by_key(|f| f.span.2 - f.span.0)
+                .is_none_or(|f| f.hits > 0);
+            let missed: Vec<&String> = branch.arm_names.iter().zip(&branch.arms)
+                .filter(|(_, taken)| taken.unwrap_or(0) == 0)
+                .map(|(name, _)| name)
+                .collect();
+            if called && !missed.is_empty() {
+                let arms = missed.iter().map(|name| format!("`{}`", name)).collect::<Vec<_>>().join(", ");
+                results.push(result(1, format!("`{}`: {} never taken", branch.text, arms), &file.path, &branch.span));
+            }
+        }
+    }
+
+    if let Some(mutants) = mutants {
+        // the results name the file relative to its crate
+        let uri = files.iter()
+            .find(|file| Path::new(&file.path).ends_with(&mutants.file))
+            .map_or(mutants.file.as_str(), |file| file.path.as_str());
+        for r in mutants.results.iter().filter(|r| matches!(r.status, Status::Survived | Status::NotCovered)) {
+            let outcome = if r.status == Status::NotCovered { "is reached by no test" } else { "survives every test" };
+            let text = format!("Mutant #{} [{}] `{}` -> `{}` {}", r.mutant.id, r.mutant.kind, r.mutant.before, r.mutant.after, outcome);
+            results.push(result(2, text, uri, &r.mutant.span));
+        }
+    }
+
+    let rules = RULES.iter()
+        .map(|(id, description, level)| Rule {
+            id,
+            short_description: Message { text: description.to_string() },
+            default_configuration: Configuration { level },
+        })
+        .collect();
+    let driver = Driver { name: "rust-cov", version: env!("CARGO_PKG_VERSION"), rules };
+    Log { schema: SCHEMA, version: "2.1.0", runs: vec![Run { tool: Tool { driver }, column_kind: "unicodeCodePoints", results }] }
+}
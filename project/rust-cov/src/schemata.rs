@@ -0,0 +1,160 @@
+use std::collections::{BTreeMap, BTreeSet};
+use syn::visit::{self, Visit};
+
+use crate::cfg::{contains, span_of};
+use crate::mutation::{offset, Mutant, MutantType};
+use crate::Span;
+
+// Environment variable selecting the active mutant; unset or 0 runs the original.
+pub const MUTANT_VAR: &str = "RUST_COV_MUTANT";
+
+// Reads the active mutant once per mutation point.
+const MUTANT_ID_MACRO: &str = "#[allow(unused_macros)]
+macro_rules! __mutant_id {
+    () => {{
+        static ID: ::std::sync::OnceLock<usize> = ::std::sync::OnceLock::new();
+        *ID.get_or_init(|| ::std::env::var(\"VAR\").ok().and_then(|id| id.parse().ok()).unwrap_or(0))
+    }};
+}
+";
+
+// A mutation point: the byte range of the mutated node and the alternatives
+// compiled in for it, as (mutant id, replacement text).
+struct Point {
+    from: usize,
+    to: usize,
+    block: bool,
+    arms: Vec<(usize, String)>,
+}
+
+// Rewrites `source` so that every mutation point becomes
+// `match __mutant_id!() { 1 => mutant1, ..., _ => original }`. Returns the new
+// source and the ids of the mutants it contains; mutants in places that must
+// stay constant (patterns, array lengths, consts, `const fn`) are left out.
+pub fn generate(source: &str, file: &syn::File, mutants: &[&Mutant]) -> (String, BTreeSet<usize>) {
+    let mut consts = ConstRegions { spans: Vec::new() };
+    consts.visit_file(file);
+
+    let mut groups: BTreeMap<Span, Vec<&Mutant>> = BTreeMap::new();
+    for mutant in mutants {
+        if !consts.spans.iter().any(|region| contains(region, &mutant.span)) {
+            groups.entry(mutant.span).or_default().push(mutant);
+        }
+    }
+    let ids: BTreeSet<usize> = groups.values().flatten().map(|m| m.id).collect();
+    if ids.is_empty() {
+        return (source.to_string(), ids);
+    }
+
+    let mut points: Vec<Point> = groups.into_iter()
+        .map(|((start_l, start, end_l, end), mutants)| {
+            let from = offset(source, start_l, start);
+            let to = offset(source, end_l, end);
+            let arms = mutants.iter().map(|m| (m.id, mutated_text(source, from, to, m))).collect();
+            Point { from, to, block: mutants[0].kind == MutantType::Block, arms }
+        })
+        .collect();
+    // outer points first, so nested ones directly follow their parent
+    points.sort_by_key(|p| (p.from, std::cmp::Reverse(p.to)));
+
+    let rewritten = render(source, 0, source.len(), &points);
+    let at = file.attrs.iter()
+        .filter(|attr| matches!(attr.style, syn::AttrStyle::Inner(_)))
+        .map(|attr| { let span = span_of(attr); offset(source, span.2, span.3) })
+        .max()
+        .map_or(0, |end| end + source[end..].find('\n').map_or(source.len() - end, |nl| nl + 1));
+    let macro_def = MUTANT_ID_MACRO.replace("VAR", MUTANT_VAR);
+    (format!("{}{}{}", &rewritten[..at], macro_def, &rewritten[at..]), ids)
+}
+
+// The node's text with only this mutant's edit applied.
+fn mutated_text(source: &str, from: usize, to: usize, mutant: &Mutant) -> String {
+    let ((start_l, start, end_l, end), replacement) = &mutant.edit;
+    let edit_from = offset(source, *start_l, *start);
+    let edit_to = offset(source, *end_l, *end);
+    format!("{}{}{}", &source[from..edit_from], replacement, &source[edit_to..to])
+}
+
+// The text of `source[from..to]` with every point in it switched. In the
+// original arm nested points are switched too; a mutant arm keeps its nested
+// code unmutated, since only one mutant is active at a time.
+fn render(source: &str, from: usize, to: usize, points: &[Point]) -> String {
+    let mut text = String::new();
+    let mut pos = from;
+    let mut idx = 0;
+    while idx < points.len() {
+        let point = &points[idx];
+        let nested = points[idx + 1..].iter().take_while(|p| p.from < point.to).count();
+        let original = render(source, point.from, point.to, &points[idx + 1..idx + 1 + nested]);
+
+        let arms: String = point.arms.iter().map(|(id, arm)| format!("{} => {}, ", id, arm)).collect();
+        text += &source[pos..point.from];
+        text += &if point.block {
+            format!("{{ match __mutant_id!() {{ {}_ => {} }} }}", arms, original)
+        } else {
+            format!("(match __mutant_id!() {{ {}_ => {} }})", arms, original)
+        };
+        pos = point.to;
+        idx += 1 + nested;
+    }
+    text += &source[pos..to];
+    text
+}
+
+// Code evaluated at compile time, where the active mutant cannot be looked up.
+struct ConstRegions {
+    spans: Vec<Span>,
+}
+
+impl<'ast> Visit<'ast> for ConstRegions {
+    fn visit_pat(&mut self, i: &'ast syn::Pat) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_type(&mut self, i: &'ast syn::Type) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_expr_repeat(&mut self, i: &'ast syn::ExprRepeat) {
+        self.spans.push(span_of(&i.len));
+        visit::visit_expr_repeat(self, i);
+    }
+
+    fn visit_expr_const(&mut self, i: &'ast syn::ExprConst) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_item_const(&mut self, i: &'ast syn::ItemConst) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_item_static(&mut self, i: &'ast syn::ItemStatic) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_impl_item_const(&mut self, i: &'ast syn::ImplItemConst) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_generic_argument(&mut self, i: &'ast syn::GenericArgument) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_signature(&mut self, i: &'ast syn::Signature) {
+        self.spans.push(span_of(i));
+    }
+
+    fn visit_item_fn(&mut self, i: &'ast syn::ItemFn) {
+        if i.sig.constness.is_some() {
+            self.spans.push(span_of(i));
+        }
+        visit::visit_item_fn(self, i);
+    }
+
+    fn visit_impl_item_fn(&mut self, i: &'ast syn::ImplItemFn) {
+        if i.sig.constness.is_some() {
+            self.spans.push(span_of(i));
+        }
+        visit::visit_impl_item_fn(self, i);
+    }
+}
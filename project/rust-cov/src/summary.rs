@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, BTreeSet};
+use quote::ToTokens;
+
+use crate::cfg::{span_of, Cfg, EdgeKind};
+use crate::edge::EdgeCoverage;
+use crate::hits::LineHits;
+use crate::mcdc::ConditionCounts;
+use crate::mutation::offset;
+use crate::probe;
+use crate::{Coverage, Span};
+
+// A function with its execution count and cyclomatic complexity.
+pub struct Function {
+    pub name: String,
+    pub span: Span,
+    pub hits: u64,
+    pub complexity: usize,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum BranchKind {
+    // the true and false edges of an `if` or `while`, the ok and err edges of `?`
+    Jump,
+    // the arms of a `match`
+    Switch,
+    // one condition of a compound decision, true and false
+    Condition(usize),
+}
+
+// A decision and how often each of its arms was taken, `None` when the
+// decision never ran.
+pub struct Branch {
+    pub line: usize,
+    // the condition, or the first pattern of a `match`
+    pub span: Span,
+    pub kind: BranchKind,
+    // the condition, or the scrutinee's arms for a `match`
+    pub text: String,
+    pub arm_names: Vec<String>,
+    pub arms: Vec<Option<u64>>,
+}
+
+impl Branch {
+    pub fn covered(&self) -> usize {
+        self.arms.iter().filter(|taken| taken.is_some_and(|count| count > 0)).count()
+    }
+}
+
+// The coverage of one file in terms of rust-cov's items, as the report formats
+// consume it.
+pub struct FileCoverage {
+    pub path: String,
+    pub source: String,
+    pub functions: Vec<Function>,
+    // execution count of every line starting a function, statement or decision
+    pub lines: BTreeMap<usize, u64>,
+    // every statement with the execution count of its first line
+    pub statements: Vec<(Span, u64)>,
+    // every loop with how often its body ran, 0 when it was never entered
+    pub loops: Vec<(Span, u64)>,
+    pub branches: Vec<Branch>,
+}
+
+impl FileCoverage {
+    // With decision evaluations from `rust-cov trace`, each condition of a
+    // compound decision becomes a branch of its own.
+    pub fn new(path: &str, source: &str, coverage: &Coverage, cfgs: &[Cfg], hits: &LineHits, conditions: Option<&ConditionCounts>) -> Self {
+        let functions = coverage.func_cov.iter()
+            .map(|(idx, (name, span))| Function {
+                name: name.clone(),
+                span: *span,
+                hits: hits.count(span.0),
+                complexity: coverage.complexity.get(idx).map_or(1, |(cyclomatic, _)| *cyclomatic),
+            })
+            .collect();
+        let lines: BTreeMap<usize, u64> = coverable_lines(coverage, cfgs).into_iter().map(|line| (line, hits.count(line))).collect();
+        let statements = coverage.stmt_cov.values().map(|span| (*span, hits.count(span.0))).collect();
+        // the body is the lines after the header, a loop on one line has only its header
+        let loops = coverage.loop_cov.values()
+            .map(|span| {
+                let body = lines.range(span.0 + 1..=span.2).map(|(_, count)| *count).max();
+                (*span, body.unwrap_or_else(|| hits.count(span.0)))
+            })
+            .collect();
+
+        let mut branches = Vec::new();
+        for coverage in cfgs.iter().map(|cfg| EdgeCoverage::new(cfg, hits)) {
+            // the edges leaving one block on a condition form one decision
+            let mut decisions: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+            for (e, edge) in coverage.cfg.edges.iter().enumerate().filter(|(_, edge)| edge.cond.is_some()) {
+                decisions.entry(edge.from).or_default().push(e);
+            }
+            for edges in decisions.values() {
+                let edge = &coverage.cfg.edges[edges[0]];
+                let Some((span, cond)) = &edge.cond else {
+                    continue;
+                };
+                // a `match` is located by its first pattern, which may get no hits
+                let evaluated = hits.count(span.0) > 0 || edges.iter().any(|e| coverage.counts[*e] > 0);
+                let is_if = matches!(edge.kind, EdgeKind::True | EdgeKind::False);
+                let (kind, text, arm_names) = if !matches!(edge.kind, EdgeKind::Arm(_)) {
+                    (BranchKind::Jump, cond.clone(), edges.iter().map(|e| coverage.cfg.edges[*e].kind.to_string()).collect())
+                } else {
+                    // arm edges carry their pattern
+                    let patterns = edges.iter().map(|e| coverage.cfg.edges[*e].cond.as_ref().map_or(String::new(), |(_, pat)| pat.clone()));
+                    (BranchKind::Switch, "match".to_string(), patterns.collect())
+                };
+                let arms = edges.iter().map(|e| evaluated.then_some(coverage.counts[*e])).collect();
+                branches.push(Branch { line: span.0, span: *span, kind, text, arm_names, arms });
+
+                let counts = conditions.and_then(|conditions| conditions.get(&(span.0, span.1)));
+                if let Some(counts) = counts.filter(|counts| is_if && counts.len() > 1) {
+                    let parts = condition_spans(source, span);
+                    for (idx, [taken, not_taken]) in counts.iter().enumerate() {
+                        let (span, text) = parts.get(idx).cloned().unwrap_or_else(|| (*span, format!("condition {}", idx + 1)));
+                        branches.push(Branch {
+                            line: span.0,
+                            span,
+                            kind: BranchKind::Condition(idx),
+                            text,
+                            arm_names: vec!["true".to_string(), "false".to_string()],
+                            arms: vec![evaluated.then_some(*taken), evaluated.then_some(*not_taken)],
+                        });
+                    }
+                }
+            }
+        }
+        branches.sort_by_key(|branch| branch.line);
+
+        Self { path: path.to_string(), source: source.to_string(), functions, lines, statements, loops, branches }
+    }
+
+    // The line declaring the function, past its attributes, without the
+    // opening brace.
+    pub fn signature(&self, function: &Function) -> String {
+        let mut lines = self.source.lines().skip(function.span.0 - 1).take(function.span.2 + 1 - function.span.0);
+        let line = lines.find(|line| line.contains("fn ")).unwrap_or("");
+        line.trim().trim_end_matches('{').trim_end().to_string()
+    }
+
+    pub fn functions_covered(&self) -> usize {
+        self.functions.iter().filter(|f| f.hits > 0).count()
+    }
+
+    pub fn statements_covered(&self) -> usize {
+        self.statements.iter().filter(|(_, count)| *count > 0).count()
+    }
+
+    pub fn loops_covered(&self) -> usize {
+        self.loops.iter().filter(|(_, count)| *count > 0).count()
+    }
+
+    pub fn lines_covered(&self) -> usize {
+        self.lines.values().filter(|count| **count > 0).count()
+    }
+
+    pub fn arms(&self) -> usize {
+        self.branches.iter().map(|branch| branch.arms.len()).sum()
+    }
+
+    pub fn arms_covered(&self) -> usize {
+        self.branches.iter().map(Branch::covered).sum()
+    }
+}
+
+// The conditions of the decision at `span`, located in the file: the decision's
+// own text is parsed again, so its spans are relative to the decision.
+fn condition_spans(source: &str, span: &Span) -> Vec<(Span, String)> {
+    let text = &source[offset(source, span.0, span.1)..offset(source, span.2, span.3)];
+    let Ok(expr) = syn::parse_str::<syn::Expr>(text) else {
+        return Vec::new();
+    };
+    let shift = |(line, col): (usize, usize)| (span.0 + line - 1, if line == 1 { span.1 + col } else { col });
+    probe::conditions(&expr).iter()
+        .map(|c| {
+            let (start, end) = (shift((span_of(*c).0, span_of(*c).1)), shift((span_of(*c).2, span_of(*c).3)));
+            ((start.0, start.1, end.0, end.1), c.to_token_stream().to_string())
+        })
+        .collect()
+}
+
+// The lines starting a function, statement or decision.
+pub fn coverable_lines(coverage: &Coverage, cfgs: &[Cfg]) -> BTreeSet<usize> {
+    coverage.func_cov.values().map(|(_, span)| span.0)
+        .chain(coverage.stmt_cov.values().map(|span| span.0))
+        .chain(cfgs.iter().flat_map(|cfg| cfg.edges.iter().filter_map(|edge| edge.cond.as_ref().map(|(span, _)| span.0))))
+        .collect()
+}